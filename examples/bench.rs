@@ -0,0 +1,88 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: bench.rs                           //
+// Description: Times headless execution of //
+//              a ROM.                      //
+// ---------------------------------------- //
+// Usage:                                   //
+//   cargo run --release --example bench -- //
+//     [cycles] [runs] [memory size] [ROM]  //
+// Compare results from the same machine.   //
+// ---------------------------------------- //
+
+use chip8_rust::chip8::*;
+
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+/// Instructions to run per timed run when no count is given.
+const DEFAULT_CYCLES: u64 = 30_000_000;
+/// Timed runs when no count is given.
+const DEFAULT_RUNS: usize = 15;
+/// Instructions to run between timer ticks (roughly 600 IPS at 60 Hz).
+const TICKS_PER_FRAME: u64 = 10;
+
+/// A loop that stores and loads registers, then draws a font glyph, so memory reads, writes and the screen all get
+/// exercised: I = 0x300; V0 += 1; store V0-V1; I = 0x300; load V0-V1; I = font; draw at (V0, V1); V1 += 1; repeat.
+const LOOP_ROM: &[u8] = &[
+    0xA3, 0x00, 0x70, 0x01, 0xF1, 0x55, 0xA3, 0x00, 0xF1, 0x65, 0xA0, 0x50, 0xD0, 0x15, 0x71, 0x01, 0x12, 0x00,
+];
+
+/// Main entry point.
+fn main() -> Result<(), Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let usage = || Error::new(ErrorKind::InvalidInput, "Usage: bench [cycles] [runs] [memory size] [ROM file]");
+    let cycles: u64 = match args.first() {
+        Some(value) => value.parse().map_err(|_| usage())?,
+        None => DEFAULT_CYCLES,
+    };
+    let runs: usize = match args.get(1) {
+        Some(value) => value.parse().ok().filter(|runs| *runs > 0).ok_or_else(usage)?,
+        None => DEFAULT_RUNS,
+    };
+    let memory_size: Option<usize> = match args.get(2) {
+        Some(value) => Some(value.parse().map_err(|_| usage())?),
+        None => None,
+    };
+    let rom: Vec<u8> = match args.get(3) {
+        Some(path) => fs::read(path)?,
+        None => LOOP_ROM.to_vec(),
+    };
+
+    let mut times: Vec<Duration> = Vec::with_capacity(runs);
+    for _ in 0 .. runs {
+        let mut builder: Chip8Builder = Chip8::builder();
+        if let Some(size) = memory_size {
+            builder = builder.memory_size(size);
+        }
+        let mut chip8: Chip8 = builder.build();
+        chip8.load_rom_bytes(&rom)?;
+
+        let start: Instant = Instant::now();
+        for cycle in 0 .. cycles {
+            chip8.step_instruction().map_err(|e| Error::other(format!("Cycle {}: {}", cycle, e)))?;
+            if cycle % TICKS_PER_FRAME == TICKS_PER_FRAME - 1 {
+                chip8.tick_timers();
+            }
+        }
+        times.push(start.elapsed());
+    }
+
+    times.sort_unstable();
+    let median: Duration = times[times.len() / 2];
+    println!(
+        "{} cycles x {} runs: min {:.3}s, median {:.3}s, max {:.3}s ({:.1} ns per instruction at the median)",
+        cycles,
+        runs,
+        times[0].as_secs_f64(),
+        median.as_secs_f64(),
+        times[times.len() - 1].as_secs_f64(),
+        median.as_nanos() as f64 / cycles.max(1) as f64
+    );
+    Ok(())
+}
@@ -0,0 +1,170 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: fuzz.rs                            //
+// Description: Runs random and mutated     //
+//              ROMs looking for panics.    //
+// ---------------------------------------- //
+// Usage:                                   //
+//   cargo run --release --example fuzz --  //
+//     [iterations] [seed] [corpus dir]     //
+// The same seed always runs the same ROMs. //
+// ---------------------------------------- //
+
+use chip8_rust::chip8::*;
+use chip8_rust::quirks::Quirks;
+
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::ExitCode;
+
+/// Iterations to run when none are given.
+const DEFAULT_ITERATIONS: u64 = 10_000;
+/// Instructions to run per ROM at most.
+const MAX_CYCLES: usize = 5_000;
+/// Instructions to run between timer ticks (roughly 600 IPS at 60 Hz).
+const TICKS_PER_FRAME: usize = 10;
+/// Longest random ROM in bytes.
+const MAX_RANDOM_LENGTH: usize = 512;
+
+/// Small known-good programs to mutate, covering loops, calls, drawing, BCD, register copies and key waits.
+const SEED_CORPUS: &[&[u8]] = &[
+    // Draw every font digit across the screen
+    &[0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF2, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x72, 0x01, 0x32, 0x10, 0x12, 0x06, 0x12, 0x12],
+    // Call a subroutine that stores the BCD of V0 and loads it back
+    &[0x60, 0xFE, 0xA3, 0x00, 0x22, 0x08, 0x12, 0x06, 0xF0, 0x33, 0xF2, 0x65, 0x00, 0xEE],
+    // ALU ops on V0 and V1, with VF results
+    &[0x60, 0x0F, 0x61, 0xF0, 0x80, 0x14, 0x80, 0x15, 0x80, 0x16, 0x80, 0x17, 0x80, 0x1E, 0x80, 0x11, 0x80, 0x12, 0x80, 0x13, 0x12, 0x00],
+    // Wait for a key, then skip on it
+    &[0xF0, 0x0A, 0xE0, 0x9E, 0x12, 0x00, 0xE0, 0xA1, 0x12, 0x00],
+    // Timers, random numbers and a store near the end of memory
+    &[0x60, 0x10, 0xF0, 0x15, 0xF0, 0x18, 0xF1, 0x07, 0xC2, 0xFF, 0xAF, 0xFE, 0xF2, 0x55, 0xB2, 0x00],
+];
+
+/// Main entry point.
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// Runs the fuzzer, returning false if any ROM panicked.
+fn run() -> Result<bool, Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let usage = || Error::new(ErrorKind::InvalidInput, "Usage: fuzz [iterations] [seed] [corpus dir]");
+    let iterations: u64 = match args.first() {
+        Some(value) => value.parse().map_err(|_| usage())?,
+        None => DEFAULT_ITERATIONS,
+    };
+    let seed: u64 = match args.get(1) {
+        Some(value) => value.parse().map_err(|_| usage())?,
+        None => 0,
+    };
+
+    // Add any ROMs from the corpus directory to the built-in seeds
+    let mut corpus: Vec<Vec<u8>> = SEED_CORPUS.iter().map(|rom| rom.to_vec()).collect();
+    if let Some(dir) = args.get(2) {
+        for entry in fs::read_dir(dir)? {
+            corpus.push(fs::read(entry?.path())?);
+        }
+    }
+
+    // Keep the default panic message quiet, the report below says what happened
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut failures: u64 = 0;
+    for iteration in 0 .. iterations {
+        let rom_seed: u64 = seed.wrapping_add(iteration);
+        let mut rng: fastrand::Rng = fastrand::Rng::with_seed(rom_seed);
+        let rom: Vec<u8> = generate_rom(&mut rng, &corpus);
+        let builder: Chip8Builder = random_config(&mut rng);
+
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| run_rom(builder, &rom, &mut rng, rom_seed)));
+        if let Err(payload) = outcome {
+            let message: &str = payload.downcast_ref::<&str>().copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("(no message)");
+            let path: String = format!("fuzz-crash-{}.ch8", rom_seed);
+            fs::write(&path, &rom)?;
+            eprintln!("Seed {} panicked: {}", rom_seed, message);
+            eprintln!("  ROM saved to {}", path);
+            failures += 1;
+        }
+    }
+
+    println!("Ran {} ROMs from seed {}: {} panicked.", iterations, seed, failures);
+    Ok(failures == 0)
+}
+
+/// Generates either random bytes or a mutated corpus ROM.
+fn generate_rom(rng: &mut fastrand::Rng, corpus: &[Vec<u8>]) -> Vec<u8> {
+    if rng.bool() {
+        return (0 .. rng.usize(1 ..= MAX_RANDOM_LENGTH)).map(|_| rng.u8(..)).collect();
+    }
+
+    let mut rom: Vec<u8> = corpus[rng.usize(.. corpus.len())].clone();
+    for _ in 0 .. rng.usize(1 ..= 8) {
+        let index: usize = rng.usize(.. rom.len().max(1));
+        match rng.u8(.. 3) {
+            0 if !rom.is_empty() => rom[index] = rng.u8(..),
+            1 if !rom.is_empty() => rom[index] ^= 1 << rng.u8(.. 8),
+            _ => rom.insert(index.min(rom.len()), rng.u8(..)),
+        }
+    }
+    rom
+}
+
+/// Picks a random interpreter configuration.
+fn random_config(rng: &mut fastrand::Rng) -> Chip8Builder {
+    let quirks: Quirks = Quirks {
+        vf_reset: rng.bool(),
+        shift: rng.bool(),
+        store_increment: rng.bool(),
+        load_increment: rng.bool(),
+        index_overflow_flag: rng.bool(),
+        vf_row_count: rng.bool(),
+        wrap_x: rng.bool(),
+        wrap_y: rng.bool(),
+        clip_start: rng.bool(),
+        draws_per_frame: rng.bool().then(|| rng.u32(1 ..= 4)),
+    };
+    Chip8::builder()
+        .strict(rng.bool())
+        .protect_font(rng.bool())
+        .detect_idle(rng.bool())
+        .extended_opcodes(rng.bool())
+        .record_collisions(rng.bool())
+        .stack_depth(rng.u8(1 ..= 16))
+        // Sizes under 4K are clamped up to it, so plain CHIP-8 memory comes up often too
+        .memory_size(rng.usize(..= MAX_MEMORY_SIZE))
+        .quirks(quirks)
+}
+
+/// Runs a ROM until it errors, stops or reaches the cycle limit, pressing random keys along the way.
+fn run_rom(builder: Chip8Builder, rom: &[u8], rng: &mut fastrand::Rng, seed: u64) {
+    // CXNN uses the global generator, so seed it too to keep runs repeatable
+    fastrand::seed(seed);
+    let mut chip8: Chip8 = builder.build();
+    if chip8.load_rom_bytes(rom).is_err() {
+        return;
+    }
+
+    for cycle in 0 .. MAX_CYCLES {
+        if cycle % TICKS_PER_FRAME == 0 {
+            chip8.tick_timers();
+            chip8.set_key(rng.u8(.. 16), rng.bool());
+        }
+        if chip8.cycle().is_err() || chip8.status().is_stopped() {
+            return;
+        }
+    }
+}
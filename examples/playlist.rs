@@ -0,0 +1,144 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: playlist.rs                        //
+// Description: Example ROM browser that    //
+//              cycles through a directory. //
+// ---------------------------------------- //
+// Usage:                                   //
+//   cargo run --example playlist -- <dir>  //
+// Page Up/Down switch ROMs, Escape quits.  //
+// ---------------------------------------- //
+
+use chip8_rust::chip8::*;
+use chip8_rust::keypad;
+
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+/// Factor by which to scale the window up.
+const SCALE_FACTOR: u32 = 8;
+/// Instructions to run per frame (roughly 600 IPS at 60 Hz).
+const TICKS_PER_FRAME: usize = 10;
+
+/// Main entry point.
+fn main() -> Result<(), Error> {
+    let dir: String = env::args().nth(1)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Usage: playlist <ROM directory>"))?;
+
+    // Collect the ROMs in a stable order
+    let mut roms: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ch8")))
+        .collect();
+    roms.sort();
+    if roms.is_empty() {
+        return Err(Error::new(ErrorKind::NotFound, format!("No .ch8 files found in '{}'.", dir)));
+    }
+
+    // Initialize SDL
+    let sdl_context = sdl2::init().map_err(Error::other)?;
+    let video_subsystem = sdl_context.video().map_err(Error::other)?;
+    let window = video_subsystem
+        .window("chip8-rust playlist", SCREEN_WIDTH as u32 * SCALE_FACTOR, SCREEN_HEIGHT as u32 * SCALE_FACTOR)
+        .position_centered()
+        .build()
+        .map_err(Error::other)?;
+    let mut canvas = window.into_canvas().present_vsync().build().map_err(Error::other)?;
+    let mut event_pump = sdl_context.event_pump().map_err(Error::other)?;
+
+    // Start on the first ROM that loads
+    let mut chip8: Chip8 = Chip8::builder().detect_idle(true).build();
+    let mut current: usize = load_next(&mut chip8, &roms, roms.len() - 1, 1)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "None of the ROMs could be loaded."))?;
+    canvas.window_mut().set_title(&title(&roms[current])).map_err(Error::other)?;
+
+    'execute: loop {
+        let mut step: Option<isize> = None;
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'execute,
+                Event::KeyDown { keycode: Some(Keycode::PageDown), .. } => step = Some(1),
+                Event::KeyDown { keycode: Some(Keycode::PageUp), .. } => step = Some(-1),
+                Event::KeyDown { keycode: Some(key), .. } => {
+                    if let Some(key_val) = process_key(key) {
+                        chip8.set_key(key_val as u8, true);
+                    }
+                },
+                Event::KeyUp { keycode: Some(key), .. } => {
+                    if let Some(key_val) = process_key(key) {
+                        chip8.set_key(key_val as u8, false);
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // Run a frame, moving on to the next ROM if this one fails
+        if let Err(e) = chip8.run_frame(TICKS_PER_FRAME) {
+            eprintln!("{}: {}", roms[current].display(), e);
+            step = Some(1);
+        }
+
+        if let Some(step) = step {
+            current = load_next(&mut chip8, &roms, current, step)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "None of the ROMs could be loaded."))?;
+            canvas.window_mut().set_title(&title(&roms[current])).map_err(Error::other)?;
+        }
+
+        // Draw the screen
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        canvas.set_draw_color(Color::WHITE);
+        let width: usize = chip8.width();
+        for (index, pixel) in chip8.framebuffer().iter().enumerate() {
+            if *pixel {
+                let x: i32 = (index % width) as i32 * SCALE_FACTOR as i32;
+                let y: i32 = (index / width) as i32 * SCALE_FACTOR as i32;
+                canvas.fill_rect(Rect::new(x, y, SCALE_FACTOR, SCALE_FACTOR)).map_err(Error::other)?;
+            }
+        }
+        canvas.present();
+    }
+
+    Ok(())
+}
+
+/// Resets the interpreter and loads the ROM `step` places from `current`, skipping ROMs that fail to load.
+/// Returns the index of the loaded ROM, or None if none of them load.
+fn load_next(chip8: &mut Chip8, roms: &[PathBuf], current: usize, step: isize) -> Option<usize> {
+    let mut index: usize = current;
+    for _ in 0 .. roms.len() {
+        index = (index as isize + step).rem_euclid(roms.len() as isize) as usize;
+        chip8.reset();
+        match chip8.load_rom(&roms[index].to_string_lossy().into_owned()) {
+            Ok(()) => {
+                println!("Playing {} ({}/{})", roms[index].display(), index + 1, roms.len());
+                return Some(index);
+            },
+            Err(e) => eprintln!("Skipping {}: {}", roms[index].display(), e),
+        }
+    }
+    None
+}
+
+/// Builds the window title for a ROM.
+fn title(rom: &Path) -> String {
+    let name: String = rom.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    format!("chip8-rust playlist - {}", name)
+}
+
+/// Converts a keycode into a keypad index by the character on the key.
+fn process_key(key: Keycode) -> Option<usize> {
+    char::from_u32(key.into_i32() as u32).and_then(keypad::key_for_char)
+}
@@ -0,0 +1,77 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: audio.rs                           //
+// Description: Fading the buzzer in and    //
+//              out.                        //
+// ---------------------------------------- //
+
+use rodio::Source;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long the buzzer takes to fade in or out.
+pub const FADE_DURATION: Duration = Duration::from_millis(5);
+
+/// Wraps an endless tone so it fades in while a gate is open and out while it's closed, rather than cutting off
+/// mid-wave and clicking like pausing the sink does.
+pub struct Envelope<S: Source<Item = f32>> {
+    /// The tone being shaped.
+    source: S,
+    /// Whether the tone should be heard, set from the sound timer callback.
+    gate: Arc<AtomicBool>,
+    /// The current volume, from 0 (silent) to 1.
+    gain: f32,
+    /// How much the volume moves per sample, so a full fade takes `FADE_DURATION`.
+    step: f32,
+}
+
+impl<S: Source<Item = f32>> Envelope<S> {
+    /// Wraps a tone, starting silent.
+    pub fn new(source: S, gate: Arc<AtomicBool>) -> Envelope<S> {
+        let fade_samples: f32 = source.sample_rate() as f32 * source.channels() as f32 * FADE_DURATION.as_secs_f32();
+        Envelope {
+            source,
+            gate,
+            gain: 0.0,
+            step: 1.0 / fade_samples.max(1.0),
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Envelope<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample: f32 = self.source.next()?;
+        if self.gate.load(Ordering::Relaxed) {
+            self.gain = (self.gain + self.step).min(1.0);
+        }
+        else {
+            self.gain = (self.gain - self.step).max(0.0);
+        }
+        Some(sample * self.gain)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Envelope<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
@@ -0,0 +1,164 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: chip8-tui.rs                       //
+// Description: Terminal front-end, for     //
+//              running without a display.  //
+// ---------------------------------------- //
+// Usage:                                   //
+//   cargo run --features tui --bin         //
+//     chip8-tui -- <ROM file path>         //
+// Escape or Ctrl+C quits.                  //
+// ---------------------------------------- //
+
+use chip8_rust::chip8::*;
+use chip8_rust::keypad;
+
+use std::env;
+use std::io::{self, Error, ErrorKind, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+/// Instructions to run per frame (roughly 600 IPS at 60 Hz).
+const TICKS_PER_FRAME: usize = 10;
+/// Length of a 60 Hz frame.
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+/// Frames a key stays held after a press, for terminals that don't report key releases.
+const KEY_HOLD_FRAMES: u8 = 6;
+
+/// Puts the terminal into raw mode on the alternate screen, and restores it when dropped.
+struct TerminalGuard {
+    /// Whether key releases are reported.
+    reports_releases: bool,
+}
+
+impl TerminalGuard {
+    /// Prepares the terminal for drawing and reading keys.
+    fn new(stdout: &mut Stdout) -> Result<TerminalGuard, Error> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        let reports_releases: bool = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            execute!(stdout, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+        Ok(TerminalGuard { reports_releases })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout: Stdout = io::stdout();
+        if self.reports_releases {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Main entry point.
+fn main() -> Result<(), Error> {
+    let rom_path: String = env::args().nth(1)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Usage: chip8-tui <ROM file path>"))?;
+
+    let mut chip8: Chip8 = Chip8::new();
+    chip8.load_rom(&rom_path)?;
+
+    // Ring the terminal bell when the buzzer starts
+    chip8.set_sound_callback(|audible| {
+        if audible {
+            print!("\x07");
+        }
+    });
+
+    let mut stdout: Stdout = io::stdout();
+    let guard: TerminalGuard = TerminalGuard::new(&mut stdout)?;
+
+    // Frames left before each key is released, when the terminal can't say
+    let mut held_frames: [u8; 16] = [0; 16];
+
+    'execute: loop {
+        let frame_start: Instant = Instant::now();
+
+        // Read keys until the frame is due
+        while let Some(timeout) = FRAME_TIME.checked_sub(frame_start.elapsed()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            if let Event::Key(key) = event::read()? {
+                if is_quit(&key) {
+                    break 'execute;
+                }
+                let Some(key_val) = process_key(key.code) else { continue };
+                match key.kind {
+                    KeyEventKind::Press | KeyEventKind::Repeat => {
+                        chip8.set_key(key_val as u8, true);
+                        held_frames[key_val] = KEY_HOLD_FRAMES;
+                    },
+                    KeyEventKind::Release => chip8.set_key(key_val as u8, false),
+                }
+            }
+        }
+        if !guard.reports_releases {
+            for (key_val, frames) in held_frames.iter_mut().enumerate() {
+                if *frames > 0 {
+                    *frames -= 1;
+                    if *frames == 0 {
+                        chip8.set_key(key_val as u8, false);
+                    }
+                }
+            }
+        }
+
+        chip8.run_frame(TICKS_PER_FRAME)?;
+
+        if chip8.take_display_dirty() {
+            draw_screen(&chip8, &mut stdout)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether a key event should quit the emulator.
+fn is_quit(key: &KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && (key.code == KeyCode::Esc || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+}
+
+/// Draws the screen with half-block characters, so each terminal cell shows two pixels stacked vertically.
+fn draw_screen(chip8: &Chip8, stdout: &mut Stdout) -> Result<(), Error> {
+    let width: usize = chip8.width();
+    for cell_y in 0 .. chip8.height() / 2 {
+        let line: String = (0 .. width)
+            .map(|x| {
+                let top: bool = chip8.framebuffer()[cell_y * 2 * width + x];
+                let bottom: bool = chip8.framebuffer()[(cell_y * 2 + 1) * width + x];
+                match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                }
+            })
+            .collect();
+        queue!(stdout, MoveTo(0, cell_y as u16), Print(line))?;
+    }
+    stdout.flush()
+}
+
+/// Converts a key into a keypad index by the character on it.
+fn process_key(key: KeyCode) -> Option<usize> {
+    let KeyCode::Char(c) = key else { return None };
+    keypad::key_for_char(c)
+}
@@ -7,7 +7,12 @@
 // Description: CHIP-8 guts.                //
 // ---------------------------------------- //
 
+use crate::error::Chip8Error;
 use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+#[cfg(feature = "profiling")]
+use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fs::{self, Metadata};
 use std::io::{Error, ErrorKind};
 
@@ -17,12 +22,18 @@ const PC_START_ADDRESS: u16 = 0x200;
 pub const SCREEN_WIDTH: u8 = 64;
 /// Represents the screen height in pixels.
 pub const SCREEN_HEIGHT: u8 = 32;
-/// Represents amount of RAM in bytes.
-const MEMORY_SIZE: u16 = 4096;
+/// Represents the default amount of RAM in bytes.
+const MEMORY_SIZE: usize = 4096;
+/// Represents the most RAM that 16-bit addresses can reach (as on XO-CHIP).
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+/// Represents the number of SUPER-CHIP RPL user flags saved by FX75.
+pub const RPL_FLAG_COUNT: usize = 8;
+/// Represents the default number of stack frames (as on SUPER-CHIP; the COSMAC VIP had 12).
+pub const DEFAULT_STACK_DEPTH: u8 = 16;
 /// Represents the size of the system font.
-const FONT_SIZE: u16 = 80;
+pub const FONT_SIZE: u16 = 80;
 /// Represents the system font.
-const FONT_DATA: [u8; FONT_SIZE as usize] = [
+pub const FONT_DATA: [u8; FONT_SIZE as usize] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -40,36 +51,501 @@ const FONT_DATA: [u8; FONT_SIZE as usize] = [
     0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
-/// Represents the system font start address.
+/// Represents the default system font start address.
 const FONT_START_ADDRESS: u16 = 0x50;
+/// Represents the highest font start address that keeps the font clear of the program area.
+pub const MAX_FONT_ADDRESS: u16 = PC_START_ADDRESS - FONT_SIZE;
+/// Represents the default sound timer value above which the buzzer is audible.
+///
+/// This is 1 rather than 0, so a timer of 1 is silent and beeps end one frame
+/// early. That matches the original main loop and some interpreters, which
+/// treat the final tick as silent.
+pub const DEFAULT_SOUND_THRESHOLD: u8 = 1;
 
-// Quirks
-const VF_RESET_MATH_QUIRK: bool = true;
+/// Represents a callback that receives the values of each ALU opcode.
+type AluCallback = Box<dyn FnMut(&AluEvent)>;
+/// Represents a callback that receives the RPL flags after FX75 saves them.
+type RplCallback = Box<dyn FnMut(&[u8; RPL_FLAG_COUNT])>;
+/// Represents a handler that runs otherwise-unknown opcodes, returning whether it handled the opcode.
+type UnknownHandler = Box<dyn FnMut(&mut Chip8, u16) -> bool>;
 
 /// Represents the underlying CHIP-8 system.
 pub struct Chip8 {
     /// Represents general purpose registers V0-VF.
     reg_v: [u8; 16],
-    /// Represents 4K of RAM.
-    memory: [u8; MEMORY_SIZE as usize],
+    /// Represents RAM, 4K unless configured otherwise.
+    memory: Vec<u8>,
     /// Stores a memory address for later use in an operation.
     reg_i: u16,
     /// Points to the current instruction in memory.
     pc: u16,
-    /// Represents the 16-frame stack.
-    stack: [u16; 16],
+    /// Represents the stack, sized to the configured depth.
+    stack: Vec<u16>,
     /// Points to the current stack frame.
     sp: u8,
     /// Represents the 60Hz delay timer register.
     reg_delay: u8,
     /// Represents the 60Hz sound timer register.
-    pub reg_sound: u8,
+    reg_sound: u8,
     /// Holds the state of the 16 input keys.
-    pub keypad: [bool; 16],
-    /// Holds the state of the graphics buffer.
-    pub graphics_buffer: [bool; ((SCREEN_WIDTH as u16) * (SCREEN_HEIGHT as u16)) as usize],
+    keypad: [bool; 16],
+    /// Held keys in the order they were pressed.
+    press_order: Vec<u8>,
+    /// Which key FX0A picks when several are held.
+    await_key_mode: AwaitKeyMode,
+    /// Holds the state of the graphics buffer, one entry per pixel of the active resolution, row-major.
+    graphics_buffer: Vec<bool>,
+    /// The width of the active display resolution in pixels.
+    screen_width: usize,
+    /// The height of the active display resolution in pixels.
+    screen_height: usize,
     /// Holds the current instruction being decoded.
     instr: Instruction,
+    /// Called when the sound timer crosses the audibility threshold.
+    sound_callback: Option<Box<dyn FnMut(bool)>>,
+    /// Called with the intermediate values of each arithmetic or logic opcode.
+    alu_callback: Option<AluCallback>,
+    /// Called when the delay timer is set or runs out.
+    delay_callback: Option<Box<dyn FnMut(DelayEvent)>>,
+    /// Called with the RPL flags after FX75 saves them.
+    rpl_callback: Option<RplCallback>,
+    /// Holds the SUPER-CHIP RPL user flags, which FX75 and FX85 copy registers to and from.
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    /// Runs opcodes the interpreter doesn't know before they're treated as unknown.
+    unknown_handler: Option<UnknownHandler>,
+    /// The sound timer value above which the buzzer is audible.
+    sound_threshold: u8,
+    /// Whether undefined behavior raises errors instead of being tolerated.
+    strict: bool,
+    /// Holds the active compatibility quirks.
+    quirks: Quirks,
+    /// Counts the DXYN draws made since the last frame boundary.
+    frame_draws: u32,
+    /// How 0NNN machine language calls are handled.
+    machine_code: MachineCodeBehavior,
+    /// How DXYN combines sprites with the screen.
+    draw_mode: DrawMode,
+    /// Whether execution has stopped.
+    halted: bool,
+    /// Whether writes into the font region are blocked.
+    protect_font: bool,
+    /// Where the system font starts in memory.
+    font_address: u16,
+    /// Whether a jump to its own address stops execution.
+    detect_idle: bool,
+    /// Whether delay timer wait loops are fast-forwarded.
+    skip_delay_loops: bool,
+    /// Whether the extended 5XY1-5XY3, FX75 and FX85 opcodes are decoded.
+    extended_opcodes: bool,
+    /// Whether execution stopped at a self-jump.
+    idle: bool,
+    /// Whether FX0A found no key held last time it ran.
+    waiting: bool,
+    /// Draw counters for the current frame.
+    draw_stats: DrawStats,
+    /// Screen indices where the last DXYN collided, if recording.
+    collisions: Option<Vec<usize>>,
+    /// Whether the screen may have changed since the front-end last checked.
+    display_dirty: bool,
+    /// The number of instructions that can be undone; 0 disables recording.
+    undo_depth: usize,
+    /// Changes made by the most recent instructions, oldest first.
+    undo_log: VecDeque<StepDelta>,
+    /// The size of the loaded ROM in bytes.
+    rom_size: usize,
+    /// Fixed bytes for CXNN to cycle through instead of random ones, if not empty.
+    rng_sequence: Vec<u8>,
+    /// Index of the next byte in rng_sequence.
+    rng_index: usize,
+    /// Every distinct opcode executed since the last reset.
+    #[cfg(feature = "profiling")]
+    executed_opcodes: HashSet<u16>,
+}
+
+/// Represents how 0NNN machine language calls are handled, since no interpreter can run RCA 1802 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineCodeBehavior {
+    /// Fail with an unsupported instruction error.
+    Error,
+    /// Ignore the call and continue with the next instruction.
+    Skip,
+    /// Stop execution at the call.
+    Halt,
+}
+
+/// Represents how DXYN combines sprite pixels with the screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrawMode {
+    /// Toggle screen pixels, erasing lit ones (standard CHIP-8).
+    #[default]
+    Xor,
+    /// Turn screen pixels on without ever erasing them. VF still reports sprite pixels that landed on lit ones.
+    Overwrite,
+}
+
+/// Represents which key FX0A picks when several are held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AwaitKeyMode {
+    /// The lowest-numbered held key.
+    #[default]
+    LowestIndex,
+    /// The held key that was pressed first, going by the order of `Chip8::set_key` calls.
+    FirstPress,
+}
+
+/// Represents what DXYN did to the screen during the current frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    /// Number of sprites drawn.
+    pub sprites: u32,
+    /// Number of pixels turned on.
+    pub pixels_on: u32,
+    /// Number of pixels turned off.
+    pub pixels_off: u32,
+}
+
+/// Represents an arithmetic or logic opcode, as reported to the ALU callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    /// 7XNN: VX += NN
+    AddImmediate,
+    /// 8XY1: VX |= VY
+    Or,
+    /// 8XY2: VX &= VY
+    And,
+    /// 8XY3: VX ^= VY
+    Xor,
+    /// 8XY4: VX += VY
+    Add,
+    /// 8XY5: VX -= VY
+    Sub,
+    /// 8XY6: VX = VY >> 1 (or VX >> 1 under the shift quirk)
+    ShiftRight,
+    /// 8XY7: VX = VY - VX
+    SubReverse,
+    /// 8XYE: VX = VY << 1 (or VX << 1 under the shift quirk)
+    ShiftLeft,
+}
+
+/// Represents the intermediate values of one arithmetic or logic opcode, for showing how it worked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AluEvent {
+    /// The operation.
+    pub op: AluOp,
+    /// The left operand: VX, or the shifted register for shifts (VY for 8XY7).
+    pub left: u8,
+    /// The right operand: VY, NN for 7XNN, VX for 8XY7, or the shift distance for shifts.
+    pub right: u8,
+    /// The value written to VX.
+    pub result: u8,
+    /// The value written to VF, if the operation writes it.
+    pub flag: Option<u8>,
+    /// Whether the low nibbles carried (addition) or borrowed (subtraction), as a 4-bit ALU would show.
+    pub half_carry: bool,
+}
+
+/// Represents a change to the delay timer worth reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelayEvent {
+    /// FX15 set the delay timer.
+    Set,
+    /// The delay timer counted down to 0.
+    Expired,
+}
+
+/// Represents what one instruction changed, holding just enough of the old state to reverse it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepDelta {
+    /// The program counter before the instruction.
+    pc: u16,
+    /// The opcode that ran.
+    opcode: u16,
+    /// The previously decoded opcode.
+    previous_opcode: u16,
+    /// The index register before the instruction.
+    reg_i: u16,
+    /// The stack pointer before the instruction.
+    sp: u8,
+    /// The delay timer before the instruction.
+    reg_delay: u8,
+    /// The sound timer before the instruction.
+    reg_sound: u8,
+    /// Whether execution had halted.
+    halted: bool,
+    /// Whether execution had stopped at a self-jump.
+    idle: bool,
+    /// Whether FX0A was waiting for a key.
+    waiting: bool,
+    /// The frame's draw count before the instruction.
+    frame_draws: u32,
+    /// The frame's draw counters before the instruction.
+    draw_stats: DrawStats,
+    /// The position in the fixed CXNN sequence before the instruction.
+    rng_index: usize,
+    /// V registers that changed, with their old values.
+    registers: Vec<(u8, u8)>,
+    /// Memory cells that changed, with their old values.
+    memory: Vec<(u16, u8)>,
+    /// The stack slot that changed, with its old value.
+    stack: Option<(u8, u16)>,
+    /// RPL flags that changed, with their old values.
+    rpl_flags: Vec<(u8, u8)>,
+    /// Screen pixels that were toggled.
+    pixels: Vec<u16>,
+    /// Whether running the instruction again gives the same result.
+    repeatable: bool,
+}
+
+impl StepDelta {
+    /// Gets the address of the instruction.
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Gets the opcode of the instruction.
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    /// Checks whether running the instruction again after undoing it gives the same result. This is false for
+    /// CXNN using real randomness, since the random number generator can't be wound back.
+    pub fn is_repeatable(&self) -> bool {
+        self.repeatable
+    }
+}
+
+/// Represents what one instruction did, as returned by `Chip8::step_traced`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepResult {
+    /// The address of the instruction.
+    pub pc_before: u16,
+    /// The opcode that ran.
+    pub opcode: u16,
+    /// The mnemonic of the opcode (e.g. "ADD V1, #02"), or None if it isn't a valid instruction.
+    pub mnemonic: Option<String>,
+    /// V registers that changed, as (register, old value, new value).
+    pub changed_registers: Vec<(u8, u8, u8)>,
+    /// Memory cells that changed, as (address, old value, new value).
+    pub changed_memory: Vec<(u16, u8, u8)>,
+    /// RPL flags that changed, as (flag, old value, new value).
+    pub changed_flags: Vec<(u8, u8, u8)>,
+}
+
+/// Represents whether the interpreter is still making progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Instructions are being executed.
+    Running,
+    /// The program jumped to its own address and will spin forever; execution has stopped.
+    Idle,
+    /// Execution was stopped by a `0NNN` call under `MachineCodeBehavior::Halt`.
+    Halted,
+    /// FX0A is waiting for a key; execution resumes once one is held.
+    Waiting,
+}
+
+impl Status {
+    /// Checks whether execution has stopped for good, either idle or halted.
+    pub fn is_stopped(self) -> bool {
+        matches!(self, Status::Idle | Status::Halted)
+    }
+}
+
+/// Builds a Chip8 with non-default configuration.
+#[derive(Default)]
+pub struct Chip8Builder {
+    /// Whether undefined behavior raises errors instead of being tolerated.
+    strict: bool,
+    /// Compatibility quirks to use.
+    quirks: Quirks,
+    /// How 0NNN is handled; defaults to erroring when strict and skipping otherwise.
+    machine_code: Option<MachineCodeBehavior>,
+    /// The sound timer value above which the buzzer is audible.
+    sound_threshold: Option<u8>,
+    /// The number of stack frames.
+    stack_depth: Option<u8>,
+    /// Whether writes into the font region are blocked.
+    protect_font: bool,
+    /// Whether a jump to its own address stops execution.
+    detect_idle: bool,
+    /// Whether delay timer wait loops are fast-forwarded.
+    skip_delay_loops: bool,
+    /// How DXYN combines sprites with the screen.
+    draw_mode: DrawMode,
+    /// The number of instructions that can be undone.
+    undo_depth: usize,
+    /// Whether the extended 5XY1-5XY3, FX75 and FX85 opcodes are decoded.
+    extended_opcodes: bool,
+    /// Whether DXYN records which pixels collided.
+    record_collisions: bool,
+    /// Where the system font is loaded.
+    font_address: Option<u16>,
+    /// The amount of RAM in bytes.
+    memory_size: Option<usize>,
+    /// The RPL flags to start with.
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    /// Which key FX0A picks when several are held.
+    await_key_mode: AwaitKeyMode,
+}
+
+impl Chip8Builder {
+    /// Initializes a builder with the default configuration.
+    pub fn new() -> Chip8Builder {
+        Chip8Builder::default()
+    }
+
+    /// Sets whether undefined behavior raises errors instead of being tolerated.
+    ///
+    /// Strict mode adds the following checks:
+    /// * Memory accesses outside of RAM fail instead of wrapping around.
+    /// * Fetching from an odd PC, or from beyond RAM, fails.
+    /// * Calling past the stack depth or returning from an empty stack fails instead of wrapping.
+    /// * 0NNN machine language calls fail instead of being skipped, unless configured otherwise.
+    /// * Unknown opcodes fail instead of being skipped.
+    /// * FX29 with a digit above 0xF fails instead of using the low nibble.
+    /// * EX9E and EXA1 with a key above 0xF fail instead of using the low nibble.
+    pub fn strict(mut self, strict: bool) -> Chip8Builder {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the compatibility quirks.
+    pub fn quirks(mut self, quirks: Quirks) -> Chip8Builder {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Sets how 0NNN machine language calls are handled.
+    pub fn machine_code(mut self, behavior: MachineCodeBehavior) -> Chip8Builder {
+        self.machine_code = Some(behavior);
+        self
+    }
+
+    /// Sets the sound timer value above which the buzzer is audible (see DEFAULT_SOUND_THRESHOLD).
+    pub fn sound_threshold(mut self, threshold: u8) -> Chip8Builder {
+        self.sound_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the number of nested subroutine calls allowed (see DEFAULT_STACK_DEPTH), at least 1.
+    pub fn stack_depth(mut self, depth: u8) -> Chip8Builder {
+        self.stack_depth = Some(depth.max(1));
+        self
+    }
+
+    /// Sets whether writes into the font region are blocked (an error in strict mode, a warning otherwise).
+    pub fn protect_font(mut self, protect: bool) -> Chip8Builder {
+        self.protect_font = protect;
+        self
+    }
+
+    /// Sets whether a 1NNN jump to its own address stops execution with an idle status (see `Chip8::status`).
+    pub fn detect_idle(mut self, detect: bool) -> Chip8Builder {
+        self.detect_idle = detect;
+        self
+    }
+
+    /// Sets whether the delay timer wait loop `FX07; 3X00; 1NNN` (jumping back to the FX07) is fast-forwarded:
+    /// the timers are ticked until the delay timer runs out and execution continues after the loop, instead of
+    /// running the loop every frame. The loop reads no keys, so no input is missed, but time passes faster than
+    /// the caller's frames, so this is only for running unattended (tests and benchmarks). Not done while
+    /// recording undo history.
+    pub fn skip_delay_loops(mut self, skip: bool) -> Chip8Builder {
+        self.skip_delay_loops = skip;
+        self
+    }
+
+    /// Sets whether the extended opcodes are decoded: 5XY1 (CHIP-8E; skip if VX > VY), the XO-CHIP
+    /// 5XY2 (store VX..VY at I) and 5XY3 (load VX..VY from I), and the SUPER-CHIP FX75 (store V0..VX in the
+    /// RPL flags) and FX85 (load V0..VX from them). The range opcodes count down when X > Y and don't change I,
+    /// and FX75/FX85 only take X up to 7. Otherwise they're unknown opcodes.
+    pub fn extended_opcodes(mut self, enabled: bool) -> Chip8Builder {
+        self.extended_opcodes = enabled;
+        self
+    }
+
+    /// Sets how DXYN combines sprites with the screen. Anything other than `DrawMode::Xor` breaks normal ROMs,
+    /// and is meant for tooling that draws static debug sprites.
+    pub fn draw_mode(mut self, mode: DrawMode) -> Chip8Builder {
+        self.draw_mode = mode;
+        self
+    }
+
+    /// Sets where the system font is loaded and where FX29 points (0x050 by default; some COSMAC VIP setups used
+    /// 0x000). Addresses past `MAX_FONT_ADDRESS` are lowered to it, so the font never overlaps the program.
+    pub fn font_address(mut self, addr: u16) -> Chip8Builder {
+        self.font_address = Some(addr.min(MAX_FONT_ADDRESS));
+        self
+    }
+
+    /// Sets the amount of RAM in bytes, between the default 4096 and `MAX_MEMORY_SIZE` (64K, as on XO-CHIP).
+    /// Addresses past the end still wrap around or fail in strict mode.
+    pub fn memory_size(mut self, size: usize) -> Chip8Builder {
+        self.memory_size = Some(size.clamp(MEMORY_SIZE, MAX_MEMORY_SIZE));
+        self
+    }
+
+    /// Sets the RPL flags the machine starts with, e.g. high scores saved by an earlier run.
+    pub fn rpl_flags(mut self, flags: [u8; RPL_FLAG_COUNT]) -> Chip8Builder {
+        self.rpl_flags = flags;
+        self
+    }
+
+    /// Sets which key FX0A picks when several are held. `AwaitKeyMode::FirstPress` relies on keys being pressed
+    /// through `Chip8::set_key`.
+    pub fn await_key_mode(mut self, mode: AwaitKeyMode) -> Chip8Builder {
+        self.await_key_mode = mode;
+        self
+    }
+
+    /// Sets whether each DXYN records the screen indices where it collided (see `Chip8::last_collisions`).
+    /// Meant for tests and debugging tools; defaults to off, which skips recording entirely.
+    pub fn record_collisions(mut self, record: bool) -> Chip8Builder {
+        self.record_collisions = record;
+        self
+    }
+
+    /// Sets how many of the most recent instructions `Chip8::undo_step` can reverse. Defaults to 0, which skips
+    /// recording entirely.
+    pub fn undo_depth(mut self, depth: usize) -> Chip8Builder {
+        self.undo_depth = depth;
+        self
+    }
+
+    /// Builds the configured Chip8.
+    pub fn build(self) -> Chip8 {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.strict = self.strict;
+        chip8.quirks = self.quirks;
+        chip8.protect_font = self.protect_font;
+        chip8.detect_idle = self.detect_idle;
+        chip8.skip_delay_loops = self.skip_delay_loops;
+        chip8.extended_opcodes = self.extended_opcodes;
+        chip8.draw_mode = self.draw_mode;
+        chip8.undo_depth = self.undo_depth;
+        chip8.collisions = self.record_collisions.then(Vec::new);
+        chip8.await_key_mode = self.await_key_mode;
+        chip8.rpl_flags = self.rpl_flags;
+        if self.memory_size.is_some() || self.font_address.is_some() {
+            chip8.memory = vec![0; self.memory_size.unwrap_or(MEMORY_SIZE)];
+            chip8.font_address = self.font_address.unwrap_or(FONT_START_ADDRESS);
+            chip8.load_font();
+        }
+        chip8.sound_threshold = self.sound_threshold.unwrap_or(DEFAULT_SOUND_THRESHOLD);
+        chip8.stack = vec![0; self.stack_depth.unwrap_or(DEFAULT_STACK_DEPTH) as usize];
+        chip8.machine_code = match (self.machine_code, self.strict) {
+            (Some(behavior), _) => behavior,
+            (None, true) => MachineCodeBehavior::Error,
+            (None, false) => MachineCodeBehavior::Skip,
+        };
+        chip8
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Core Chip8 function implementations.
@@ -78,21 +554,60 @@ impl Chip8 {
     pub fn new() -> Chip8 {
         let mut chip8: Chip8 = Chip8 {
             reg_v: [0; 16],
-            memory: [0; MEMORY_SIZE as usize],
+            memory: vec![0; MEMORY_SIZE],
             reg_i: 0,
             pc: PC_START_ADDRESS,
-            stack: [0; 16],
+            stack: vec![0; DEFAULT_STACK_DEPTH as usize],
             sp: 0,
             reg_delay: 0,
             reg_sound: 0,
             keypad: [false; 16],
-            graphics_buffer: [false; ((SCREEN_WIDTH as u16) * (SCREEN_HEIGHT as u16)) as usize],
+            press_order: Vec::new(),
+            await_key_mode: AwaitKeyMode::LowestIndex,
+            graphics_buffer: vec![false; SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize],
+            screen_width: SCREEN_WIDTH as usize,
+            screen_height: SCREEN_HEIGHT as usize,
             instr: Instruction { raw: 0 },
+            sound_callback: None,
+            alu_callback: None,
+            delay_callback: None,
+            rpl_callback: None,
+            rpl_flags: [0; RPL_FLAG_COUNT],
+            unknown_handler: None,
+            sound_threshold: DEFAULT_SOUND_THRESHOLD,
+            strict: false,
+            quirks: Quirks::default(),
+            frame_draws: 0,
+            machine_code: MachineCodeBehavior::Skip,
+            draw_mode: DrawMode::Xor,
+            halted: false,
+            protect_font: false,
+            font_address: FONT_START_ADDRESS,
+            detect_idle: false,
+            skip_delay_loops: false,
+            extended_opcodes: false,
+            idle: false,
+            waiting: false,
+            draw_stats: DrawStats::default(),
+            collisions: None,
+            display_dirty: true,
+            undo_depth: 0,
+            undo_log: VecDeque::new(),
+            rom_size: 0,
+            rng_sequence: Vec::new(),
+            rng_index: 0,
+            #[cfg(feature = "profiling")]
+            executed_opcodes: HashSet::new(),
         };
         chip8.load_font();
         chip8
     }
-    
+
+    /// Initializes a builder for a Chip8 with non-default configuration.
+    pub fn builder() -> Chip8Builder {
+        Chip8Builder::new()
+    }
+
     /// Resets the state of the Chip8 struct.
     pub fn reset(&mut self) {
         self.reg_v.fill(0);
@@ -102,10 +617,23 @@ impl Chip8 {
         // Ignore stack, no need to fully clear.
         self.sp = 0;
         self.reg_delay = 0;
-        self.reg_sound = 0;
+        self.set_sound_timer(0);
         self.keypad.fill(false);
+        self.press_order.clear();
         self.clear_screen();
         self.instr = Instruction { raw: 0 };
+        self.frame_draws = 0;
+        self.draw_stats = DrawStats::default();
+        if let Some(collisions) = self.collisions.as_mut() {
+            collisions.clear();
+        }
+        self.halted = false;
+        self.idle = false;
+        self.waiting = false;
+        self.rom_size = 0;
+        self.undo_log.clear();
+        #[cfg(feature = "profiling")]
+        self.executed_opcodes.clear();
         self.load_font();
     }
 
@@ -113,63 +641,456 @@ impl Chip8 {
     pub fn load_rom(&mut self, rom_path: &String) -> Result<(), Error> {
         // Get file size and max ROM size (RAM size - PC start)
         let file_attributes: Metadata = fs::metadata(rom_path)?;
-        let available_memory: u64 = (MEMORY_SIZE - PC_START_ADDRESS) as u64;
+        let available_memory: u64 = (self.memory.len() - PC_START_ADDRESS as usize) as u64;
         if file_attributes.len() > available_memory {
             return Err(Error::new(ErrorKind::OutOfMemory, "ROM size exceeded available memory space."));
         }
 
         // Read in file and write to memory
         let rom_bytes: Vec<u8> = fs::read(rom_path)?;
-        let rom_memory_region: &mut [u8] = &mut (self.memory)[PC_START_ADDRESS as usize .. MEMORY_SIZE as usize];
-        for (dst, src) in rom_memory_region.iter_mut().zip(&rom_bytes) {
+        self.load_rom_bytes(&rom_bytes)
+    }
+
+    /// Attempts to load a ROM from a byte slice.
+    pub fn load_rom_bytes(&mut self, rom_bytes: &[u8]) -> Result<(), Error> {
+        let available_memory: usize = self.memory.len() - PC_START_ADDRESS as usize;
+        if rom_bytes.len() > available_memory {
+            return Err(Error::new(ErrorKind::OutOfMemory, "ROM size exceeded available memory space."));
+        }
+
+        let rom_memory_region: &mut [u8] = &mut (self.memory)[PC_START_ADDRESS as usize ..];
+        for (dst, src) in rom_memory_region.iter_mut().zip(rom_bytes) {
             *dst = *src;
         }
+        self.rom_size = rom_bytes.len();
 
         Ok(())
     }
 
-    /// Attempts to cycle the interpreter by one instruction.
-    pub fn cycle(&mut self) {
-        self.fetch();
-        self.execute();
+    /// Attempts to load a ROM from hex dump text (e.g. `00E0 A22A ...`).
+    ///
+    /// Tokens are separated by whitespace and may be bytes (`A2`) or words
+    /// (`A22A`), optionally prefixed with `0x`. Anything after a `;` on a line
+    /// is treated as a comment.
+    pub fn load_rom_hex(&mut self, text: &str) -> Result<(), Error> {
+        let mut rom_bytes: Vec<u8> = Vec::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            // Strip comments
+            let code: &str = match line.find(';') {
+                Some(comment_start) => &line[.. comment_start],
+                None => line,
+            };
+
+            for token in code.split_whitespace() {
+                let digits: &str = token.strip_prefix("0x")
+                    .or_else(|| token.strip_prefix("0X"))
+                    .unwrap_or(token);
+                let is_hex: bool = digits.chars().all(|c| c.is_ascii_hexdigit());
+                let parsed: Option<u16> = match digits.len() {
+                    2 | 4 if is_hex => u16::from_str_radix(digits, 16).ok(),
+                    _ => None,
+                };
+
+                match (parsed, digits.len()) {
+                    (Some(byte), 2) => rom_bytes.push(byte as u8),
+                    (Some(word), _) => rom_bytes.extend_from_slice(&word.to_be_bytes()),
+                    (None, _) => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Invalid hex token '{}' on line {}.", token, line_index + 1),
+                        ));
+                    },
+                }
+            }
+        }
+
+        self.load_rom_bytes(&rom_bytes)
+    }
+
+    /// Attempts to cycle the interpreter by one instruction; a convenience for `step_instruction`.
+    pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+        self.step_instruction()
+    }
+
+    /// Attempts to fetch and execute one instruction. Does nothing once halted or idle.
+    ///
+    /// This and `tick_timers` are the two timing primitives: run as many instructions per frame as the
+    /// desired speed calls for (e.g. 10 per frame for 600 IPS), then tick the timers once. Neither
+    /// depends on wall-clock time, so the same sequence of calls always replays the same way.
+    pub fn step_instruction(&mut self) -> Result<(), Chip8Error> {
+        if self.halted || self.idle {
+            return Ok(());
+        }
+
+        if self.undo_depth == 0 {
+            if self.skip_delay_loops && self.reg_delay > 0 {
+                if let Some(reg) = self.delay_loop_at_pc() {
+                    self.fast_forward_delay_loop(reg);
+                    return Ok(());
+                }
+            }
+            return self.fetch_and_execute();
+        }
+
+        let (delta, result): (StepDelta, Result<(), Chip8Error>) = self.tracked_step();
+        self.record_undo(delta);
+        result
+    }
+
+    /// Attempts to run one instruction like `step_instruction`, returning what it did and changed for a debugger
+    /// to show, or None once halted or idle. This allocates, so front-ends running at speed should use `cycle`.
+    ///
+    /// Register changes made by an unknown opcode handler are reported like any others, but its memory writes
+    /// aren't (see `set_unknown_handler`). With undo recording on, the instruction can be undone as usual.
+    pub fn step_traced(&mut self) -> Result<Option<StepResult>, Chip8Error> {
+        if self.halted || self.idle {
+            return Ok(None);
+        }
+
+        let (delta, result): (StepDelta, Result<(), Chip8Error>) = self.tracked_step();
+        let step: StepResult = StepResult {
+            pc_before: delta.pc,
+            opcode: delta.opcode,
+            mnemonic: Instruction { raw: delta.opcode }.disassemble(),
+            changed_registers: delta.registers.iter()
+                .map(|(reg, old)| (*reg, *old, self.reg_v[*reg as usize]))
+                .collect(),
+            changed_memory: delta.memory.iter()
+                .map(|(addr, old)| (*addr, *old, self.memory[*addr as usize]))
+                .collect(),
+            changed_flags: delta.rpl_flags.iter()
+                .map(|(flag, old)| (*flag, *old, self.rpl_flags[*flag as usize]))
+                .collect(),
+        };
+        if self.undo_depth > 0 {
+            self.record_undo(delta);
+        }
+        result?;
+        Ok(Some(step))
+    }
+
+    /// Runs one instruction, keeping the old values of whatever the opcode can touch and returning the ones
+    /// that changed. The screen is only compared when recording undo history.
+    fn tracked_step(&mut self) -> (StepDelta, Result<(), Chip8Error>) {
+        let reg_v: [u8; 16] = self.reg_v;
+        let delta: StepDelta = self.begin_delta();
+        let screen: Option<Vec<bool>> = (self.undo_depth > 0 && (delta.opcode == 0x00E0 || delta.opcode & 0xF000 == 0xD000))
+            .then(|| self.graphics_buffer.to_vec());
+        let result: Result<(), Chip8Error> = self.fetch_and_execute();
+        (self.finish_delta(delta, &reg_v, screen.as_deref()), result)
+    }
+
+    /// Gets the register of a `FX07; 3X00; 1NNN` delay timer wait loop starting at the PC, if there is one.
+    /// 1NNN only reaches the first 4K, so there's never one past it.
+    fn delay_loop_at_pc(&self) -> Option<usize> {
+        if self.pc >= 0x1000 {
+            return None;
+        }
+        let word = |offset: u16| -> Option<u16> {
+            let addr: u16 = self.pc.checked_add(offset)?;
+            Some((self.peek(addr).ok()? as u16) << 8 | self.peek(addr.checked_add(1)?).ok()? as u16)
+        };
+        let (read, check, jump): (u16, u16, u16) = (word(0)?, word(2)?, word(4)?);
+        let reg: u16 = (read >> 8) & 0xF;
+        let is_loop: bool = read & 0xF0FF == 0xF007 && check == 0x3000 | reg << 8
+            && jump & 0xF000 == 0x1000 && jump & 0x0FFF == self.pc;
+        is_loop.then_some(reg as usize)
+    }
+
+    /// Runs a delay timer wait loop to completion at once: ticks the timers until the delay timer reaches 0,
+    /// then leaves the PC after the loop with VX = 0, as the last pass through the loop would.
+    fn fast_forward_delay_loop(&mut self, reg: usize) {
+        while self.reg_delay > 0 {
+            self.tick_timers();
+        }
+        self.reg_v[reg] = 0;
+        self.pc = self.pc.wrapping_add(6);
+    }
+
+    /// Fetches and executes the instruction at the PC.
+    fn fetch_and_execute(&mut self) -> Result<(), Chip8Error> {
+        self.fetch()?;
+        #[cfg(feature = "profiling")]
+        self.executed_opcodes.insert(self.instr.raw);
+        self.execute()
+    }
+
+    /// Starts an undo record for the instruction at the PC, holding the old values of the registers it always
+    /// saves and of any memory or stack slot the opcode may write.
+    fn begin_delta(&self) -> StepDelta {
+        let opcode: u16 = u16::from_be_bytes([
+            self.peek(self.pc).unwrap_or(0),
+            self.peek(self.pc.wrapping_add(1)).unwrap_or(0),
+        ]);
+        let memory: Vec<(u16, u8)> = if opcode & 0xF0FF == 0xF033 || opcode & 0xF0FF == 0xF055 || opcode & 0xF00F == 0x5002 {
+            (0 .. 16)
+                .map(|offset: u16| self.wrap_addr(self.reg_i.wrapping_add(offset)))
+                .map(|addr| (addr, self.memory[addr as usize]))
+                .collect()
+        }
+        else {
+            Vec::new()
+        };
+        let stack: Option<(u8, u16)> = (opcode & 0xF000 == 0x2000).then(|| {
+            let slot: u8 = if self.sp as usize >= self.stack.len() { 0 } else { self.sp };
+            (slot, self.stack[slot as usize])
+        });
+        let rpl_flags: Vec<(u8, u8)> = if opcode & 0xF0FF == 0xF075 {
+            (0 .. RPL_FLAG_COUNT).map(|flag| (flag as u8, self.rpl_flags[flag])).collect()
+        }
+        else {
+            Vec::new()
+        };
+
+        StepDelta {
+            pc: self.pc,
+            opcode,
+            previous_opcode: self.instr.raw,
+            reg_i: self.reg_i,
+            sp: self.sp,
+            reg_delay: self.reg_delay,
+            reg_sound: self.reg_sound,
+            halted: self.halted,
+            idle: self.idle,
+            waiting: self.waiting,
+            frame_draws: self.frame_draws,
+            draw_stats: self.draw_stats,
+            rng_index: self.rng_index,
+            registers: Vec::new(),
+            memory,
+            stack,
+            rpl_flags,
+            pixels: Vec::new(),
+            repeatable: opcode & 0xF000 != 0xC000 || !self.rng_sequence.is_empty(),
+        }
+    }
+
+    /// Trims an undo record down to what actually changed.
+    fn finish_delta(&self, mut delta: StepDelta, reg_v: &[u8; 16], screen: Option<&[bool]>) -> StepDelta {
+        delta.registers = (0 .. 16)
+            .filter(|reg| self.reg_v[*reg] != reg_v[*reg])
+            .map(|reg| (reg as u8, reg_v[reg]))
+            .collect();
+        delta.memory.retain(|(addr, old)| self.memory[*addr as usize] != *old);
+        delta.stack = delta.stack.filter(|(slot, old)| self.stack[*slot as usize] != *old);
+        delta.rpl_flags.retain(|(flag, old)| self.rpl_flags[*flag as usize] != *old);
+        if let Some(screen) = screen {
+            delta.pixels = (0 .. screen.len())
+                .filter(|index| self.graphics_buffer[*index] != screen[*index])
+                .map(|index| index as u16)
+                .collect();
+        }
+        delta
+    }
+
+    /// Adds a step to the undo log, dropping the oldest once it's full.
+    fn record_undo(&mut self, delta: StepDelta) {
+        if self.undo_log.len() >= self.undo_depth {
+            self.undo_log.pop_front();
+        }
+        self.undo_log.push_back(delta);
+    }
+
+    /// Reverses the most recent recorded instruction, returning what it changed, or None if there's nothing
+    /// to undo (see `Chip8Builder::undo_depth`).
+    ///
+    /// Only changes made by instructions are recorded; keypad, `poke` and other front-end changes stay as
+    /// they are. After undoing CXNN, running it again rolls a new number unless a fixed sequence is set with
+    /// `set_rng_sequence` (see `StepDelta::is_repeatable`).
+    pub fn undo_step(&mut self) -> Option<StepDelta> {
+        let delta: StepDelta = self.undo_log.pop_back()?;
+        self.pc = delta.pc;
+        self.instr = Instruction { raw: delta.previous_opcode };
+        self.reg_i = delta.reg_i;
+        self.sp = delta.sp;
+        self.reg_delay = delta.reg_delay;
+        self.set_sound_timer(delta.reg_sound);
+        self.halted = delta.halted;
+        self.idle = delta.idle;
+        self.waiting = delta.waiting;
+        self.frame_draws = delta.frame_draws;
+        self.draw_stats = delta.draw_stats;
+        self.rng_index = delta.rng_index;
+        for (reg, value) in delta.registers.iter() {
+            self.reg_v[*reg as usize] = *value;
+        }
+        for (addr, value) in delta.memory.iter() {
+            self.memory[*addr as usize] = *value;
+        }
+        if let Some((slot, value)) = delta.stack {
+            self.stack[slot as usize] = value;
+        }
+        for (flag, value) in delta.rpl_flags.iter() {
+            self.rpl_flags[*flag as usize] = *value;
+        }
+        if !delta.rpl_flags.is_empty() {
+            // Let anything persisting the flags catch up with the restored values
+            if let Some(callback) = self.rpl_callback.as_mut() {
+                callback(&self.rpl_flags);
+            }
+        }
+        for index in delta.pixels.iter() {
+            self.graphics_buffer[*index as usize] ^= true;
+        }
+        if !delta.pixels.is_empty() {
+            self.display_dirty = true;
+        }
+        Some(delta)
     }
 
-    /// Decrements the special registers.
-    pub fn cycle_special_regs(&mut self) {
+    /// Decrements the delay and sound timers.
+    ///
+    /// Call this at 60Hz, once per frame, independently of how many instructions ran; the CHIP-8 timers count
+    /// down at that rate. It also marks a frame boundary for the `draws_per_frame` quirk and `draw_stats`.
+    pub fn tick_timers(&mut self) {
+        self.frame_draws = 0;
+        self.draw_stats = DrawStats::default();
+
         if self.reg_delay > 0 {
             self.reg_delay -= 1;
+            if self.reg_delay == 0 {
+                self.report_delay(DelayEvent::Expired);
+            }
         }
         if self.reg_sound > 0 {
-            self.reg_sound -= 1;
+            self.set_sound_timer(self.reg_sound - 1);
+        }
+    }
+
+    /// Attempts to run one frame: up to `cycles` instructions, then a timer tick. Returns the status afterwards.
+    ///
+    /// This is the integration point for embedding the interpreter in another event loop. Call it once per
+    /// 60 Hz frame, after updating the keys from input (`set_key` or `set_keypad`) and before drawing (see
+    /// `take_display_dirty`); `cycles` sets the speed, e.g. 10 for 600 IPS. The frame ends early once the program
+    /// stops or waits at FX0A, since nothing more happens until the next frame's input.
+    pub fn run_frame(&mut self, cycles: usize) -> Result<Status, Chip8Error> {
+        for _ in 0 .. cycles {
+            self.step_instruction()?;
+            if self.status() != Status::Running {
+                break;
+            }
+        }
+        self.tick_timers();
+        Ok(self.status())
+    }
+
+    /// Makes CXNN use the given bytes in order, cycling back to the start, instead of random ones.
+    /// An empty sequence goes back to real randomness.
+    pub fn set_rng_sequence(&mut self, sequence: Vec<u8>) {
+        self.rng_sequence = sequence;
+        self.rng_index = 0;
+    }
+
+    /// Sets a callback to be invoked whenever the sound timer becomes audible (true) or silent (false).
+    pub fn set_sound_callback(&mut self, callback: impl FnMut(bool) + 'static) {
+        self.sound_callback = Some(Box::new(callback));
+    }
+
+    /// Sets a callback to be invoked after each 7XNN and 8XYN arithmetic or logic opcode (except 8XY0) with its
+    /// operands, result and flag, for teaching tools that show the ALU at work. Costs nothing when unset.
+    pub fn set_alu_callback(&mut self, callback: impl FnMut(&AluEvent) + 'static) {
+        self.alu_callback = Some(Box::new(callback));
+    }
+
+    /// Sets a callback to be invoked whenever FX15 sets the delay timer or it counts down to 0, for hearing or
+    /// logging how a ROM paces itself.
+    pub fn set_delay_callback(&mut self, callback: impl FnMut(DelayEvent) + 'static) {
+        self.delay_callback = Some(Box::new(callback));
+    }
+
+    /// Sets a callback to be invoked with the RPL flags whenever FX75 saves them, for persisting them the way
+    /// SUPER-CHIP calculators kept them across runs.
+    pub fn set_rpl_callback(&mut self, callback: impl FnMut(&[u8; RPL_FLAG_COUNT]) + 'static) {
+        self.rpl_callback = Some(Box::new(callback));
+    }
+
+    /// Sets a handler for opcodes the interpreter doesn't know, for prototyping new opcodes without forking.
+    /// It gets the machine with the PC already past the opcode, and returns true if it ran the opcode or false
+    /// to treat it as unknown.
+    ///
+    /// For undo and `step_traced`, what it does to V0-VF, I, the PC, the stack pointer and the timers is recorded
+    /// like any instruction's. Its writes to memory, stack slots, the screen and the RPL flags aren't, so undoing
+    /// a handled opcode leaves those in place.
+    pub fn set_unknown_handler(&mut self, handler: impl FnMut(&mut Chip8, u16) -> bool + 'static) {
+        self.unknown_handler = Some(Box::new(handler));
+    }
+
+    /// Removes the unknown opcode handler.
+    pub fn clear_unknown_handler(&mut self) {
+        self.unknown_handler = None;
+    }
+
+    /// Writes the sound timer, notifying the sound callback if audibility changed.
+    fn set_sound_timer(&mut self, value: u8) {
+        let was_audible: bool = self.is_sound_audible();
+        self.reg_sound = value;
+        let is_audible: bool = self.is_sound_audible();
+
+        if was_audible != is_audible {
+            if let Some(callback) = self.sound_callback.as_mut() {
+                callback(is_audible);
+            }
+        }
+    }
+
+    /// Cycles the interpreter until `pred` holds, returning the number of cycles taken.
+    /// Fails if `pred` still doesn't hold after `max_cycles` cycles.
+    pub fn step_until(&mut self, pred: impl Fn(&Chip8) -> bool, max_cycles: usize) -> Result<usize, Chip8Error> {
+        for cycles in 0 .. max_cycles {
+            if pred(self) {
+                return Ok(cycles);
+            }
+            self.cycle()?;
+        }
+
+        if pred(self) {
+            Ok(max_cycles)
+        }
+        else {
+            Err(Chip8Error::CycleLimitReached(max_cycles))
         }
     }
 
     /// Attempts to load the next opcode and increment the PC.
-    fn fetch(&mut self) {
-        // Ensure PC won't overrun
-        if self.pc >= MEMORY_SIZE {
-            panic!("Program counter overflowed valid memory space.");
+    fn fetch(&mut self) -> Result<(), Chip8Error> {
+        // Instructions are two bytes, so an odd PC means a jump went astray
+        if self.strict && self.pc & 0x1 != 0 {
+            return Err(Chip8Error::MisalignedProgramCounter(self.pc));
+        }
+
+        // Keep the PC within memory when lenient
+        if !self.strict {
+            self.pc = self.wrap_addr(self.pc);
         }
 
-        let opcode_raw: u16 = (self.memory[self.pc as usize] as u16) << 8
-                            | (self.memory[(self.pc + 1) as usize] as u16);
+        // The PC wraps past 0xFFFF like any other address, which only matters with 64K of memory
+        let opcode_raw: u16 = (self.read_mem(self.pc)? as u16) << 8
+                            | (self.read_mem(self.pc.wrapping_add(1))? as u16);
         self.instr = Instruction { raw: opcode_raw };
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
     /// Attempts to decode and execute the current instruction.
-    fn execute(&mut self) {
+    fn execute(&mut self) -> Result<(), Chip8Error> {
         match self.instr.nibble1() {
             0x0 => match self.instr.raw {
                 0x00E0 => self.clear_screen(),
-                0x00EE => self.return_sub(),
-                _ => self.unsupported(), // 0NNN: Execute machine lang sub
+                0x00EE => self.return_sub()?,
+                _ => self.unsupported()?, // 0NNN: Execute machine lang sub
             },
             0x1 => self.jump(),
-            0x2 => self.call_sub(),
+            0x2 => self.call_sub()?,
             0x3 => self.skip_equal_imm(),
             0x4 => self.skip_not_equal_imm(),
-            0x5 => self.skip_equal_reg(),
+            0x5 => match self.instr.nibble4() {
+                0x0 => self.skip_equal_reg(),
+                0x1 if self.extended_opcodes => self.skip_greater_reg(),
+                0x2 if self.extended_opcodes => self.store_range()?,
+                0x3 if self.extended_opcodes => self.load_range()?,
+                _ => self.unknown()?,
+            },
             0x6 => self.load_imm(),
             0x7 => self.add_imm(),
             0x8 => match self.instr.nibble4() {
@@ -182,17 +1103,20 @@ impl Chip8 {
                 0x6 => self.shift_right(),
                 0x7 => self.sub_reg_rev(),
                 0xE => self.shift_left(),
-                _ => self.unknown(),
+                _ => self.unknown()?,
+            },
+            0x9 => match self.instr.nibble4() {
+                0x0 => self.skip_not_equal_reg(),
+                _ => self.unknown()?,
             },
-            0x9 => self.skip_not_equal_reg(),
             0xA => self.load_addr(),
             0xB => self.jump_offset(),
             0xC => self.rand(),
-            0xD => self.draw_sprite(),
+            0xD => self.draw_sprite()?,
             0xE => match self.instr.nn() {
-                0x9E => self.skip_key_pressed(),
-                0xA1 => self.skip_key_not_pressed(),
-                _ => self.unknown(),
+                0x9E => self.skip_key_pressed()?,
+                0xA1 => self.skip_key_not_pressed()?,
+                _ => self.unknown()?,
             }
             0xF => match self.instr.nn() {
                 0x07 => self.load_delay(),
@@ -200,165 +1124,849 @@ impl Chip8 {
                 0x15 => self.set_delay(),
                 0x18 => self.set_sound(),
                 0x1E => self.add_addr(),
-                0x29 => self.load_digit_addr(),
-                0x33 => self.move_bcd(),
-                0x55 => self.move_regs(),
-                0x65 => self.load_regs(),
-                _ => self.unknown(),
+                0x29 => self.load_digit_addr()?,
+                0x33 => self.move_bcd()?,
+                0x55 => self.move_regs()?,
+                0x65 => self.load_regs()?,
+                0x75 if self.extended_opcodes && self.instr.x() < RPL_FLAG_COUNT => self.save_flags(),
+                0x85 if self.extended_opcodes && self.instr.x() < RPL_FLAG_COUNT => self.load_flags(),
+                _ => self.unknown()?,
             },
-            _ => self.unknown(),
+            _ => self.unknown()?,
+        }
+        Ok(())
+    }
+
+    /// Attempts to read a byte of memory, wrapping out-of-bounds addresses unless strict.
+    fn read_mem(&self, addr: u16) -> Result<u8, Chip8Error> {
+        Ok(self.memory[self.resolve_addr(addr)?])
+    }
+
+    /// Attempts to write a byte of memory, wrapping out-of-bounds addresses unless strict.
+    fn write_mem(&mut self, addr: u16, value: u8) -> Result<(), Chip8Error> {
+        let index: usize = self.resolve_addr(addr)?;
+        if self.protect_font && (self.font_address as usize .. (self.font_address + FONT_SIZE) as usize).contains(&index) {
+            if self.strict {
+                return Err(Chip8Error::ProtectedWrite(addr));
+            }
+            eprintln!("Warning: Ignored write to protected font memory at 0x{:04X} (PC 0x{:04X}).", addr, self.pc.wrapping_sub(2));
+            return Ok(());
+        }
+        self.memory[index] = value;
+        Ok(())
+    }
+
+    /// Maps an address onto a memory index according to the strictness policy.
+    fn resolve_addr(&self, addr: u16) -> Result<usize, Chip8Error> {
+        if (addr as usize) < self.memory.len() {
+            Ok(addr as usize)
+        }
+        else if self.strict {
+            Err(Chip8Error::AddressOutOfBounds(addr))
+        }
+        else {
+            Ok(self.wrap_addr(addr) as usize)
+        }
+    }
+
+    /// Wraps an address around the end of RAM.
+    fn wrap_addr(&self, addr: u16) -> u16 {
+        // Skip the division in the common in-bounds case, as this runs every cycle
+        if (addr as usize) < self.memory.len() {
+            addr
+        }
+        else {
+            (addr as usize % self.memory.len()) as u16
         }
     }
 
     /// Loads the system font into RAM.
     fn load_font(&mut self) {
-        let font_memory_region: &mut [u8] = &mut (self.memory)[FONT_START_ADDRESS as usize .. (FONT_START_ADDRESS + FONT_SIZE) as usize];
+        let font_memory_region: &mut [u8] = &mut (self.memory)[self.font_address as usize .. (self.font_address + FONT_SIZE) as usize];
         for (dst, src) in font_memory_region.iter_mut().zip(&FONT_DATA) {
             *dst = *src;
         }
     }
 }
 
-/// Opcode implementations for Chip8.
+/// State accessors for Chip8.
 impl Chip8 {
-    /// Panics on an unknown instruction.
-    fn unknown(&self) {
-        panic!("Unknown instruction: 0x{:04X}", self.instr.raw);
+    /// Gets the program counter.
+    pub fn pc(&self) -> u16 {
+        self.pc
     }
 
-    /// Panics on an unsupported instruction.
-    fn unsupported(&self) {
-        panic!("Unsupported instruction: 0x{:04X}", self.instr.raw);
+    /// Gets the I register.
+    pub fn reg_i(&self) -> u16 {
+        self.reg_i
     }
 
-    /// 00E0: Clears the video buffer.
-    fn clear_screen(&mut self) {
-        self.graphics_buffer.fill(false);
+    /// Gets the general purpose registers V0-VF.
+    pub fn reg_v(&self) -> &[u8; 16] {
+        &self.reg_v
     }
 
-    /// 00EE: Return from subroutine
-    fn return_sub(&mut self) {
-        self.sp -= 1;
-        self.pc = self.stack[self.sp as usize];
+    /// Gets the general purpose registers V0-VF for changing, for unknown opcode handlers and debuggers.
+    pub fn reg_v_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.reg_v
     }
 
-    /// 1NNN: PC = #NNN
-    fn jump(&mut self) {
-        self.pc = self.instr.nnn();
+    /// Sets the I register, for unknown opcode handlers and debuggers.
+    pub fn set_reg_i(&mut self, value: u16) {
+        self.reg_i = value;
     }
 
-    /// 2NNN: Push PC to stack, PC = #NNN
-    fn call_sub(&mut self) {
-        self.stack[self.sp as usize] = self.pc;
-        self.sp += 1;
-        self.pc = self.instr.nnn();
+    /// Gets the delay timer.
+    pub fn delay_timer(&self) -> u8 {
+        self.reg_delay
     }
 
-    /// 3XNN: Skip next if VX == #NN
-    fn skip_equal_imm(&mut self) {
-        if self.reg_v[self.instr.x()] == self.instr.nn() {
-            self.pc += 2;
-        }
+    /// Gets the sound timer.
+    pub fn sound_timer(&self) -> u8 {
+        self.reg_sound
     }
 
-    /// 4XNN: Skip next if VX != #NN
-    fn skip_not_equal_imm(&mut self) {
-        if self.reg_v[self.instr.x()] != self.instr.nn() {
-            self.pc += 2;
-        }
+    /// Checks whether the sound timer is high enough for the buzzer to sound.
+    pub fn is_sound_audible(&self) -> bool {
+        self.reg_sound > self.sound_threshold
     }
 
-    /// 5XY0: Skip next if VX == VY
-    fn skip_equal_reg(&mut self) {
-        if self.reg_v[self.instr.x()] == self.reg_v[self.instr.y()] {
-            self.pc += 2;
+    /// Checks whether execution has stopped.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Gets whether the interpreter is running, waiting for a key, idle at a self-jump, or halted.
+    pub fn status(&self) -> Status {
+        if self.halted {
+            Status::Halted
+        }
+        else if self.idle {
+            Status::Idle
+        }
+        else if self.waiting {
+            Status::Waiting
+        }
+        else {
+            Status::Running
         }
     }
 
-    /// 6XNN: VX = #NN
-    fn load_imm(&mut self) {
-        self.reg_v[self.instr.x()] = self.instr.nn();
+    /// Gets the active compatibility quirks.
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
     }
 
-    /// 7XNN: VX += #NN (Doesn't set VF on overflow)
-    fn add_imm(&mut self) {
-        self.reg_v[self.instr.x()] = self.reg_v[self.instr.x()].wrapping_add(self.instr.nn());
+    /// Gets the active compatibility quirks for changing mid-run; changes apply from the next instruction.
+    pub fn quirks_mut(&mut self) -> &mut Quirks {
+        &mut self.quirks
     }
 
-    /// 8XY0: VX = VY
-    fn load_reg(&mut self) {
-        self.reg_v[self.instr.x()] = self.reg_v[self.instr.y()];
+    /// Gets the lowest-numbered key currently held down, without waiting like FX0A does.
+    pub fn first_pressed_key(&self) -> Option<u8> {
+        self.keypad.iter().position(|pressed| *pressed).map(|key| key as u8)
     }
 
-    /// 8XY1: VX |= VY
-    fn or(&mut self) {
-        self.reg_v[self.instr.x()] |= self.reg_v[self.instr.y()];
+    /// Presses or releases a key (0x0-0xF), remembering the order of presses for `AwaitKeyMode::FirstPress`.
+    /// Keys past 0xF don't exist on the keypad and are ignored.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let Some(state) = self.keypad.get_mut(key as usize) else {
+            return;
+        };
+        *state = pressed;
+        self.press_order.retain(|held| *held != key);
+        if pressed {
+            self.press_order.push(key);
+        }
+    }
 
-        if VF_RESET_MATH_QUIRK {
-            self.reg_v[0xF] = 0;
+    /// Sets all 16 keys at once, for front-ends that poll the whole keypad each frame. Keys that didn't change
+    /// keep their place in the press order, and keys newly pressed together count as pressed in index order.
+    pub fn set_keypad(&mut self, state: [bool; 16]) {
+        for (key, pressed) in state.into_iter().enumerate() {
+            if self.keypad[key] != pressed {
+                self.set_key(key as u8, pressed);
+            }
         }
     }
 
-    /// 8XY2: VX &= VY
-    fn and(&mut self) {
-        self.reg_v[self.instr.x()] &= self.reg_v[self.instr.y()];
+    /// Gets the state of all 16 keys.
+    pub fn keypad_state(&self) -> [bool; 16] {
+        self.keypad
+    }
 
-        if VF_RESET_MATH_QUIRK {
-            self.reg_v[0xF] = 0;
+    /// Disassembles the loaded ROM into a listing with one instruction per line, from the entry point to the
+    /// end of the ROM. Words that don't decode (usually sprite data) are marked as such.
+    pub fn disassembly(&self) -> String {
+        let mut listing: String = String::new();
+        let rom: &[u8] = &self.memory[PC_START_ADDRESS as usize .. PC_START_ADDRESS as usize + self.rom_size];
+        for (index, word) in rom.chunks(2).enumerate() {
+            let addr: u16 = PC_START_ADDRESS + 2 * index as u16;
+            if let [high, low] = word {
+                listing.push_str(&listing_line(addr, *high, *low));
+            }
+            else {
+                listing.push_str(&format!("{:04X}: {:02X}    ; trailing byte\n", addr, word[0]));
+            }
         }
+        listing
     }
-    
+
+    /// Attempts to disassemble `count` words of memory starting at `addr`, in the same format as `disassembly`.
+    pub fn disassemble(&self, addr: u16, count: u16) -> Result<String, Chip8Error> {
+        let mut listing: String = String::new();
+        for index in 0 .. count {
+            let word_addr: u16 = addr.wrapping_add(2 * index);
+            listing.push_str(&listing_line(word_addr, self.peek(word_addr)?, self.peek(word_addr.wrapping_add(1))?));
+        }
+        Ok(listing)
+    }
+
+    /// Attempts to write the disassembly of the loaded ROM (see `disassembly`) to a file.
+    pub fn dump_disassembly(&self, path: &str) -> Result<(), Error> {
+        fs::write(path, self.disassembly())
+    }
+
+    /// Gets the amount of RAM in bytes.
+    pub fn memory_size(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Gets the whole of memory, for saving what a program left behind.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Gets the RPL flags, which FX75 saves and FX85 loads.
+    pub fn rpl_flags(&self) -> [u8; RPL_FLAG_COUNT] {
+        self.rpl_flags
+    }
+
+    /// Gets the size of the loaded ROM in bytes.
+    pub fn rom_size(&self) -> usize {
+        self.rom_size
+    }
+
+    /// Gets every distinct opcode executed since the last reset.
+    #[cfg(feature = "profiling")]
+    pub fn executed_opcodes(&self) -> &HashSet<u16> {
+        &self.executed_opcodes
+    }
+
+    /// Describes the loaded ROM without running it: its size, an FNV-1a hash, the instruction mnemonics found in
+    /// it (read as aligned words) and a guess at the CHIP-8 variant it was written for.
+    ///
+    /// The guess looks for opcodes only later variants define, such as 00FF or DXY0 (SUPER-CHIP) and F000 or
+    /// 5XY2 (XO-CHIP). Sprite and other data in the ROM can look like those opcodes, so treat it as a hint.
+    pub fn rom_info(&self) -> String {
+        let rom: &[u8] = &self.memory[PC_START_ADDRESS as usize .. PC_START_ADDRESS as usize + self.rom_size];
+        let mut hash: u64 = 0xCBF29CE484222325;
+        for byte in rom.iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001B3);
+        }
+
+        let opcodes: Vec<u16> = rom.chunks_exact(2).map(|word| (word[0] as u16) << 8 | word[1] as u16).collect();
+        let mut mnemonics: Vec<String> = opcodes.iter()
+            .filter_map(|opcode| Instruction { raw: *opcode }.disassemble())
+            .filter_map(|text| text.split_whitespace().next().map(str::to_string))
+            .collect();
+        mnemonics.sort_unstable();
+        mnemonics.dedup();
+
+        let is_xo_chip = |opcode: &u16| *opcode == 0xF000 || *opcode & 0xFFF0 == 0x00D0 || *opcode & 0xF0FF == 0xF03A
+            || matches!(*opcode & 0xF00F, 0x5002 | 0x5003);
+        let is_super_chip = |opcode: &u16| matches!(*opcode, 0x00FB ..= 0x00FF) || *opcode & 0xFFF0 == 0x00C0
+            || *opcode & 0xF00F == 0xD000 || matches!(*opcode & 0xF0FF, 0xF030 | 0xF075 | 0xF085);
+        let variant: &str = if opcodes.iter().any(is_xo_chip) {
+            "XO-CHIP"
+        }
+        else if opcodes.iter().any(is_super_chip) {
+            "SUPER-CHIP"
+        }
+        else {
+            "CHIP-8"
+        };
+
+        format!(
+            "Size:\t\t{} bytes\nHash:\t\t{:016X}\nInstructions:\t{}\nVariant:\t{} (guessed)\n",
+            rom.len(), hash, mnemonics.join(" "), variant
+        )
+    }
+
+    /// Lists the distinct opcodes executed so far, and those in the ROM (read as aligned words) that never were.
+    /// Data stored in the ROM shows up as never executed, so read the second list as candidates for dead code.
+    #[cfg(feature = "profiling")]
+    pub fn coverage_report(&self) -> String {
+        let rom: &[u8] = &self.memory[PC_START_ADDRESS as usize .. PC_START_ADDRESS as usize + self.rom_size];
+        let mut missed: Vec<u16> = rom.chunks_exact(2)
+            .map(|word| (word[0] as u16) << 8 | word[1] as u16)
+            .filter(|opcode| !self.executed_opcodes.contains(opcode))
+            .collect();
+        missed.sort_unstable();
+        missed.dedup();
+        let mut executed: Vec<u16> = self.executed_opcodes.iter().copied().collect();
+        executed.sort_unstable();
+
+        let format_list = |opcodes: &[u16]| opcodes.iter().map(|opcode| format!("{:04X}", opcode)).collect::<Vec<String>>().join(" ");
+        format!(
+            "Executed ({}): {}\nNever executed ({}): {}\n",
+            executed.len(), format_list(&executed), missed.len(), format_list(&missed)
+        )
+    }
+
+    /// Exports the memory layout, the disassembly of the ROM and opcode coverage as one JSON object, for external
+    /// tools. Addresses are numbers, and `end` is exclusive. The schema is:
+    ///
+    /// ```text
+    /// {
+    ///   "memory_size": 4096,
+    ///   "entry_point": 512,
+    ///   "regions": [{ "name": "reserved" | "font" | "rom" | "free", "start": 0, "end": 512 }, ...],
+    ///   "disassembly": [{ "address": 512, "opcode": 41514, "text": "LD I, #22A" | null }, ...],
+    ///   "coverage": { "executed": [41514, ...], "never_executed": [...] } | null
+    /// }
+    /// ```
+    ///
+    /// The font lies inside the reserved region unless it was moved. The disassembly reads the ROM as aligned
+    /// words from the entry point, like `disassembly`: `text` is null for words that don't decode, and a trailing
+    /// odd byte has it as its `opcode`. Coverage is only recorded in profiling builds, and is null otherwise.
+    #[cfg(feature = "serde")]
+    pub fn analysis_json(&self) -> String {
+        let rom_end: usize = PC_START_ADDRESS as usize + self.rom_size;
+        let font_end: usize = (self.font_address + FONT_SIZE) as usize;
+        let regions: Vec<serde_json::Value> = [
+            ("reserved", 0, PC_START_ADDRESS as usize),
+            ("font", self.font_address as usize, font_end),
+            ("rom", PC_START_ADDRESS as usize, rom_end),
+            ("free", rom_end, self.memory.len()),
+        ]
+            .iter()
+            .map(|(name, start, end)| serde_json::json!({ "name": name, "start": start, "end": end }))
+            .collect();
+
+        let disassembly: Vec<serde_json::Value> = self.memory[PC_START_ADDRESS as usize .. rom_end]
+            .chunks(2)
+            .enumerate()
+            .map(|(index, word)| {
+                let address: u16 = PC_START_ADDRESS + 2 * index as u16;
+                let (opcode, text): (u16, Option<String>) = match word {
+                    [high, low] => {
+                        let instr: Instruction = Instruction { raw: (*high as u16) << 8 | *low as u16 };
+                        (instr.raw, instr.disassemble())
+                    },
+                    _ => (word[0] as u16, None),
+                };
+                serde_json::json!({ "address": address, "opcode": opcode, "text": text })
+            })
+            .collect();
+
+        #[cfg(feature = "profiling")]
+        let coverage: serde_json::Value = {
+            let mut executed: Vec<u16> = self.executed_opcodes.iter().copied().collect();
+            executed.sort_unstable();
+            let mut never_executed: Vec<u16> = self.memory[PC_START_ADDRESS as usize .. rom_end]
+                .chunks_exact(2)
+                .map(|word| (word[0] as u16) << 8 | word[1] as u16)
+                .filter(|opcode| !self.executed_opcodes.contains(opcode))
+                .collect();
+            never_executed.sort_unstable();
+            never_executed.dedup();
+            serde_json::json!({ "executed": executed, "never_executed": never_executed })
+        };
+        #[cfg(not(feature = "profiling"))]
+        let coverage: serde_json::Value = serde_json::Value::Null;
+
+        serde_json::json!({
+            "memory_size": self.memory.len(),
+            "entry_point": PC_START_ADDRESS,
+            "regions": regions,
+            "disassembly": disassembly,
+            "coverage": coverage,
+        })
+        .to_string()
+    }
+
+    /// Gets what DXYN has drawn since the last timer tick, for diagnosing flicker.
+    pub fn draw_stats(&self) -> DrawStats {
+        self.draw_stats
+    }
+
+    /// Gets the screen indices (`y * width + x`) where the most recent DXYN drew a lit pixel over a lit one, in
+    /// drawing order. Empty unless recording was turned on with `Chip8Builder::record_collisions`.
+    pub fn last_collisions(&self) -> &[usize] {
+        self.collisions.as_deref().unwrap_or(&[])
+    }
+
+    /// Checks whether the screen may have changed since the last call, clearing the flag.
+    /// Front-ends can skip redrawing while this is false. Writes made directly to `graphics_buffer` aren't tracked.
+    ///
+    /// The flag covers every 00E0 and DXYN since the last call, so calling this once per frame after `run_frame` and
+    /// then drawing `graphics_buffer` shows the buffer as it stands at the end of the frame, in a single pass however
+    /// many sprites were drawn. A DXYN held back by the `draws_per_frame` quirk doesn't set it until it actually
+    /// draws in a later frame.
+    pub fn take_display_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.display_dirty, false)
+    }
+
+    /// Gets the stack pointer.
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    /// Gets the return addresses currently on the stack, oldest first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[.. self.sp as usize]
+    }
+
+    /// Gets the most recently executed opcode.
+    pub fn last_instruction(&self) -> u16 {
+        self.instr.raw
+    }
+
+    /// Gets the mnemonic of the most recently executed opcode (e.g. "LD VA, #0F"), or None if it isn't a valid
+    /// instruction.
+    pub fn last_mnemonic(&self) -> Option<String> {
+        self.instr.disassemble()
+    }
+
+    /// Gets the width of the active display resolution in pixels.
+    pub fn width(&self) -> usize {
+        self.screen_width
+    }
+
+    /// Gets the height of the active display resolution in pixels.
+    pub fn height(&self) -> usize {
+        self.screen_height
+    }
+
+    /// Gets the screen, one entry per pixel (true when lit), row-major; always `width()` x `height()` long.
+    pub fn framebuffer(&self) -> &[bool] {
+        &self.graphics_buffer
+    }
+
+    /// Switches the display to `width` x `height` pixels, clearing the screen (as hires mode switches do).
+    /// Front-ends should read `width` and `height` rather than assume 64x32.
+    ///
+    /// # Panics
+    /// Panics if `width` or `height` is 0, as sprite positions wrap around the screen size.
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        assert!(width > 0 && height > 0, "Screen size {}x{} has no pixels.", width, height);
+        self.screen_width = width;
+        self.screen_height = height;
+        self.graphics_buffer = vec![false; width * height];
+        self.display_dirty = true;
+    }
+
+    /// Renders the graphics buffer as text, one line per row ('#' for on, '.' for off).
+    pub fn render_ascii(&self) -> String {
+        let pixels: &[bool] = &self.graphics_buffer[.. self.width() * self.height()];
+        let mut text: String = String::with_capacity(pixels.len() + self.height());
+        for row in pixels.chunks(self.width()) {
+            text.extend(row.iter().map(|pixel| if *pixel { '#' } else { '.' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Attempts to read the `height`-row sprite at I, one byte per row, the way DXYN does (wrapping past the end
+    /// of RAM unless strict).
+    pub fn sprite_at_i(&self, height: u8) -> Result<Vec<u8>, Chip8Error> {
+        (0 .. height as u16).map(|row| self.read_mem(self.reg_i.wrapping_add(row))).collect()
+    }
+
+    /// Attempts to render the `height`-row sprite at I as text without drawing it, one line per row with the
+    /// row's address and byte ('#' for on, '.' for off).
+    pub fn sprite_preview(&self, height: u8) -> Result<String, Chip8Error> {
+        let mut text: String = String::new();
+        for (row, bits) in self.sprite_at_i(height)?.iter().enumerate() {
+            let addr: u16 = self.wrap_addr(self.reg_i.wrapping_add(row as u16));
+            let pixels: String = sprite_row_pixels(*bits).map(|lit| if lit { '#' } else { '.' }).collect();
+            text.push_str(&format!("{:04X}: {:02X}  {}\n", addr, bits, pixels));
+        }
+        Ok(text)
+    }
+
+    /// Reads a `width` x `height` region of the screen starting at (`x`, `y`) back as sprite bytes, for capturing
+    /// graphics drawn in the emulator. Returns one sprite per 8-pixel-wide strip from left to right, each `height`
+    /// bytes from the top row down; pixels past the region or the screen read as off. The region is no larger than
+    /// the screen, so oversized widths and heights are cut down to it.
+    pub fn screen_sprites(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<Vec<u8>> {
+        let width: usize = width.min(self.width());
+        let height: usize = height.min(self.height());
+        let end_x: usize = x.saturating_add(width);
+        let lit = |px: usize, py: usize| px < end_x && px < self.width() && py < self.height()
+            && self.graphics_buffer[py * self.width() + px];
+        (0 .. width.div_ceil(8))
+            .map(|strip| {
+                (0 .. height)
+                    .map(|row| {
+                        let py: usize = y.saturating_add(row);
+                        (0 .. 8).fold(0, |bits: u8, col| bits << 1 | lit(x.saturating_add(strip * 8 + col), py) as u8)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Summarizes the full machine state as compact text, for bug reports.
+    pub fn dump_state(&self) -> String {
+        let mut text: String = format!(
+            "PC={:04X} I={:04X} OP={:04X} SP={} DT={} ST={}\n",
+            self.pc, self.reg_i, self.instr.raw, self.sp, self.reg_delay, self.reg_sound
+        );
+        let regs: Vec<String> = self.reg_v.iter().enumerate().map(|(i, v)| format!("V{:X}={:02X}", i, v)).collect();
+        text.push_str(&regs.join(" "));
+        text.push('\n');
+        let stack: Vec<String> = self.stack().iter().map(|addr| format!("{:04X}", addr)).collect();
+        text.push_str(&format!("STACK=[{}]\n", stack.join(" ")));
+        text.push_str(&self.render_ascii());
+        text
+    }
+
+    /// Converts the graphics buffer into row-major RGBA bytes (width() x height() pixels) using the given colors.
+    pub fn framebuffer_rgba(&self, on: [u8; 4], off: [u8; 4]) -> Vec<u8> {
+        let pixels: &[bool] = &self.graphics_buffer[.. self.width() * self.height()];
+        let mut rgba: Vec<u8> = Vec::with_capacity(pixels.len() * 4);
+        for pixel in pixels {
+            rgba.extend_from_slice(if *pixel { &on } else { &off });
+        }
+        rgba
+    }
+
+    /// Attempts to copy the graphics buffer into `out`, row-major, in a format picked by the buffer's length:
+    /// * width() * height() bytes: one byte per pixel, 1 for on and 0 for off.
+    /// * width() * height() / 8 bytes: one bit per pixel, most significant bit first.
+    ///
+    /// Fails if the length matches neither.
+    pub fn copy_framebuffer_into(&self, out: &mut [u8]) -> Result<(), Chip8Error> {
+        let pixels: &[bool] = &self.graphics_buffer[.. self.width() * self.height()];
+        if out.len() == pixels.len() {
+            for (dst, src) in out.iter_mut().zip(pixels) {
+                *dst = *src as u8;
+            }
+        }
+        else if out.len() == pixels.len() / 8 {
+            for (dst, src) in out.iter_mut().zip(pixels.chunks(8)) {
+                *dst = src.iter().fold(0, |byte, pixel| byte << 1 | *pixel as u8);
+            }
+        }
+        else {
+            return Err(Chip8Error::InvalidBufferLength(out.len()));
+        }
+        Ok(())
+    }
+
+    /// Computes a stable (FNV-1a) hash of the graphics buffer.
+    pub fn framebuffer_hash(&self) -> u64 {
+        let mut hash: u64 = 0xCBF29CE484222325;
+        for pixel in self.graphics_buffer.iter() {
+            hash ^= *pixel as u64;
+            hash = hash.wrapping_mul(0x100000001B3);
+        }
+        hash
+    }
+
+    /// Attempts to read a byte of memory, failing if the address is out of bounds.
+    pub fn peek(&self, addr: u16) -> Result<u8, Chip8Error> {
+        if addr as usize >= self.memory.len() {
+            return Err(Chip8Error::AddressOutOfBounds(addr));
+        }
+        self.read_mem(addr)
+    }
+
+    /// Attempts to move execution to `addr`, for debuggers. Fails if the address is odd or leaves no room for
+    /// an instruction. Resumes execution if it had stopped at a self-jump or a halt.
+    pub fn set_program_counter(&mut self, addr: u16) -> Result<(), Chip8Error> {
+        if addr as usize >= self.memory.len() - 1 {
+            return Err(Chip8Error::AddressOutOfBounds(addr));
+        }
+        if addr & 0x1 != 0 {
+            return Err(Chip8Error::MisalignedProgramCounter(addr));
+        }
+
+        self.pc = addr;
+        self.idle = false;
+        self.halted = false;
+        self.waiting = false;
+        Ok(())
+    }
+
+    /// Attempts to write a byte of memory, failing if the address is out of bounds. Font protection does not apply.
+    pub fn poke(&mut self, addr: u16, value: u8) -> Result<(), Chip8Error> {
+        if addr as usize >= self.memory.len() {
+            return Err(Chip8Error::AddressOutOfBounds(addr));
+        }
+        self.memory[addr as usize] = value;
+        Ok(())
+    }
+}
+
+/// Opcode implementations for Chip8.
+impl Chip8 {
+    /// Passes an unknown instruction to the unknown opcode handler, if set. If it isn't handled, fails when
+    /// strict and otherwise skips it.
+    fn unknown(&mut self) -> Result<(), Chip8Error> {
+        // Take the handler out while it runs, so it can borrow the machine
+        if let Some(mut handler) = self.unknown_handler.take() {
+            let handled: bool = handler(self, self.instr.raw);
+            if self.unknown_handler.is_none() {
+                self.unknown_handler = Some(handler);
+            }
+            if handled {
+                return Ok(());
+            }
+        }
+        if self.strict {
+            return Err(Chip8Error::UnknownInstruction(self.instr.raw));
+        }
+        Ok(())
+    }
+
+    /// 0NNN: Handles a machine language call according to the configured behavior.
+    fn unsupported(&mut self) -> Result<(), Chip8Error> {
+        match self.machine_code {
+            MachineCodeBehavior::Error => return Err(Chip8Error::UnsupportedInstruction(self.instr.raw)),
+            MachineCodeBehavior::Skip => (),
+            MachineCodeBehavior::Halt => {
+                self.pc = self.pc.wrapping_sub(2);
+                self.halted = true;
+            },
+        }
+        Ok(())
+    }
+
+    /// 00E0: Clears the video buffer.
+    fn clear_screen(&mut self) {
+        self.graphics_buffer.fill(false);
+        self.display_dirty = true;
+    }
+
+    /// 00EE: Return from subroutine
+    fn return_sub(&mut self) -> Result<(), Chip8Error> {
+        if self.sp == 0 {
+            if self.strict {
+                return Err(Chip8Error::StackUnderflow);
+            }
+            self.sp = self.stack.len() as u8;
+        }
+
+        self.sp -= 1;
+        self.pc = self.stack[self.sp as usize];
+        Ok(())
+    }
+
+    /// 1NNN: PC = #NNN
+    fn jump(&mut self) {
+        if self.detect_idle && self.instr.nnn() == self.pc.wrapping_sub(2) {
+            self.idle = true;
+        }
+        self.pc = self.instr.nnn();
+    }
+
+    /// 2NNN: Push PC to stack, PC = #NNN
+    fn call_sub(&mut self) -> Result<(), Chip8Error> {
+        if self.sp as usize >= self.stack.len() {
+            if self.strict {
+                return Err(Chip8Error::StackOverflow);
+            }
+            self.sp = 0;
+        }
+
+        self.stack[self.sp as usize] = self.pc;
+        self.sp += 1;
+        self.pc = self.instr.nnn();
+        Ok(())
+    }
+
+    /// 3XNN: Skip next if VX == #NN
+    fn skip_equal_imm(&mut self) {
+        if self.reg_v[self.instr.x()] == self.instr.nn() {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    /// 4XNN: Skip next if VX != #NN
+    fn skip_not_equal_imm(&mut self) {
+        if self.reg_v[self.instr.x()] != self.instr.nn() {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    /// 5XY0: Skip next if VX == VY
+    fn skip_equal_reg(&mut self) {
+        if self.reg_v[self.instr.x()] == self.reg_v[self.instr.y()] {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    /// 5XY1: Skip next if VX > VY (CHIP-8E)
+    fn skip_greater_reg(&mut self) {
+        if self.reg_v[self.instr.x()] > self.reg_v[self.instr.y()] {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    /// 5XY2: [I..] = [VX..VY] (XO-CHIP; counts down when X > Y, and I is unchanged)
+    fn store_range(&mut self) -> Result<(), Chip8Error> {
+        for (offset, reg) in self.register_range().into_iter().enumerate() {
+            self.write_mem(self.reg_i.wrapping_add(offset as u16), self.reg_v[reg])?;
+        }
+        Ok(())
+    }
+
+    /// 5XY3: [VX..VY] = [I..] (XO-CHIP; counts down when X > Y, and I is unchanged)
+    fn load_range(&mut self) -> Result<(), Chip8Error> {
+        for (offset, reg) in self.register_range().into_iter().enumerate() {
+            self.reg_v[reg] = self.read_mem(self.reg_i.wrapping_add(offset as u16))?;
+        }
+        Ok(())
+    }
+
+    /// Gets the registers from X to Y inclusive, in the order 5XY2 and 5XY3 visit them.
+    fn register_range(&self) -> Vec<usize> {
+        let (x, y): (usize, usize) = (self.instr.x(), self.instr.y());
+        if x <= y {
+            (x ..= y).collect()
+        }
+        else {
+            (y ..= x).rev().collect()
+        }
+    }
+
+    /// 6XNN: VX = #NN
+    fn load_imm(&mut self) {
+        self.reg_v[self.instr.x()] = self.instr.nn();
+    }
+
+    /// 7XNN: VX += #NN (Doesn't set VF on overflow)
+    fn add_imm(&mut self) {
+        let left: u8 = self.reg_v[self.instr.x()];
+        let result: u8 = left.wrapping_add(self.instr.nn());
+        self.reg_v[self.instr.x()] = result;
+        self.report_alu(AluOp::AddImmediate, left, self.instr.nn(), result, None);
+    }
+
+    /// 8XY0: VX = VY
+    fn load_reg(&mut self) {
+        self.reg_v[self.instr.x()] = self.reg_v[self.instr.y()];
+    }
+
+    /// 8XY1: VX |= VY
+    fn or(&mut self) {
+        let (left, right) = (self.reg_v[self.instr.x()], self.reg_v[self.instr.y()]);
+        self.reg_v[self.instr.x()] = left | right;
+
+        if self.quirks.vf_reset {
+            self.reg_v[0xF] = 0;
+        }
+        self.report_alu(AluOp::Or, left, right, left | right, self.quirks.vf_reset.then_some(0));
+    }
+
+    /// 8XY2: VX &= VY
+    fn and(&mut self) {
+        let (left, right) = (self.reg_v[self.instr.x()], self.reg_v[self.instr.y()]);
+        self.reg_v[self.instr.x()] = left & right;
+
+        if self.quirks.vf_reset {
+            self.reg_v[0xF] = 0;
+        }
+        self.report_alu(AluOp::And, left, right, left & right, self.quirks.vf_reset.then_some(0));
+    }
+    
     /// 8XY3: VX ^= VY
     fn xor(&mut self) {
-        self.reg_v[self.instr.x()] ^= self.reg_v[self.instr.y()];
+        let (left, right) = (self.reg_v[self.instr.x()], self.reg_v[self.instr.y()]);
+        self.reg_v[self.instr.x()] = left ^ right;
         
-        if VF_RESET_MATH_QUIRK {
+        if self.quirks.vf_reset {
             self.reg_v[0xF] = 0;
         }
+        self.report_alu(AluOp::Xor, left, right, left ^ right, self.quirks.vf_reset.then_some(0));
     }
 
     /// 8XY4: VX += VY (Sets VF on overflow)
     fn add_reg(&mut self) {
-        let (result, carry) = self.reg_v[self.instr.x()].overflowing_add(self.reg_v[self.instr.y()]);
+        let (left, right) = (self.reg_v[self.instr.x()], self.reg_v[self.instr.y()]);
+        let (result, carry) = left.overflowing_add(right);
 
         self.reg_v[self.instr.x()] = result;
         self.reg_v[0xF] = carry as u8;
+        self.report_alu(AluOp::Add, left, right, result, Some(carry as u8));
     }
 
     /// 8XY5: VX -= VY (Sets VF on borrow)
     fn sub_reg(&mut self) {
-        let (result, overflow) = self.reg_v[self.instr.x()].overflowing_sub(self.reg_v[self.instr.y()]);
+        let (left, right) = (self.reg_v[self.instr.x()], self.reg_v[self.instr.y()]);
+        let (result, overflow) = left.overflowing_sub(right);
 
         self.reg_v[self.instr.x()] = result;
         self.reg_v[0xF] = !overflow as u8;
+        self.report_alu(AluOp::Sub, left, right, result, Some(!overflow as u8));
     }
 
     /// 8XY6: VX = VY >> 1 (VF is out bit)
     fn shift_right(&mut self) {
-        let out_bit: u8 = self.reg_v[self.instr.y()] & 0x1;
-        self.reg_v[self.instr.x()] = self.reg_v[self.instr.y()] >> 1;
+        let source: u8 = self.shift_source();
+        let out_bit: u8 = source & 0x1;
+        self.reg_v[self.instr.x()] = source >> 1;
         self.reg_v[0xF] = out_bit;
+        self.report_alu(AluOp::ShiftRight, source, 1, source >> 1, Some(out_bit));
     }
 
     /// 8XY7: VX = VY - VX (Sets VF on borrow)
     fn sub_reg_rev(&mut self) {
-        let (result, overflow) = self.reg_v[self.instr.y()].overflowing_sub(self.reg_v[self.instr.x()]);
+        let (left, right) = (self.reg_v[self.instr.y()], self.reg_v[self.instr.x()]);
+        let (result, overflow) = left.overflowing_sub(right);
         
         self.reg_v[self.instr.x()] = result;
         self.reg_v[0xF] = !overflow as u8;
+        self.report_alu(AluOp::SubReverse, left, right, result, Some(!overflow as u8));
     }
 
     /// 8XYE: VX = VY << 1 (VF is out bit)
     fn shift_left(&mut self) {
-        let out_bit: u8 = (self.reg_v[self.instr.y()] >> 7) & 0x1;
-        self.reg_v[self.instr.x()] = self.reg_v[self.instr.y()] << 1;
+        let source: u8 = self.shift_source();
+        let out_bit: u8 = (source >> 7) & 0x1;
+        self.reg_v[self.instr.x()] = source << 1;
         self.reg_v[0xF] = out_bit;
+        self.report_alu(AluOp::ShiftLeft, source, 1, source << 1, Some(out_bit));
+    }
+
+    /// Passes an ALU opcode's values to the ALU callback, if one is set.
+    fn report_alu(&mut self, op: AluOp, left: u8, right: u8, result: u8, flag: Option<u8>) {
+        if let Some(callback) = self.alu_callback.as_mut() {
+            let half_carry: bool = match op {
+                AluOp::AddImmediate | AluOp::Add => (left & 0xF) + (right & 0xF) > 0xF,
+                AluOp::Sub | AluOp::SubReverse => (left & 0xF) < (right & 0xF),
+                _ => false,
+            };
+            callback(&AluEvent { op, left, right, result, flag, half_carry });
+        }
+    }
+
+    /// Passes a delay timer change to the delay callback, if one is set.
+    fn report_delay(&mut self, event: DelayEvent) {
+        if let Some(callback) = self.delay_callback.as_mut() {
+            callback(event);
+        }
+    }
+
+    /// Gets the value 8XY6 and 8XYE shift: VY normally, or VX under the shift quirk, where VY is never read
+    /// and only VX and VF are written.
+    fn shift_source(&self) -> u8 {
+        if self.quirks.shift {
+            self.reg_v[self.instr.x()]
+        }
+        else {
+            self.reg_v[self.instr.y()]
+        }
     }
 
     /// 9XY0: Skip next if VX != VY
     fn skip_not_equal_reg(&mut self) {
         if self.reg_v[self.instr.x()] != self.reg_v[self.instr.y()] {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2);
         }
     }
 
@@ -374,52 +1982,131 @@ impl Chip8 {
     
     /// CXNN: VX = rand & #NN
     fn rand(&mut self) {
-        self.reg_v[self.instr.x()] = fastrand::u8(..) & self.instr.nn();
+        let random: u8 = if self.rng_sequence.is_empty() {
+            fastrand::u8(..)
+        }
+        else {
+            let value: u8 = self.rng_sequence[self.rng_index];
+            self.rng_index = (self.rng_index + 1) % self.rng_sequence.len();
+            value
+        };
+        self.reg_v[self.instr.x()] = random & self.instr.nn();
     }
 
     /// DXYN: Draws a sprite at VX, VY, size of N-bytes, sourced from the address in register I. Also sets VF if any ON pixels are set to OFF.
-    fn draw_sprite(&mut self) {
-        // Extract start coords from registers
-        let x: u8 = self.reg_v[self.instr.x() as usize] & (SCREEN_WIDTH - 1) as u8;
-        let y: u8 = self.reg_v[self.instr.y() as usize] & (SCREEN_HEIGHT - 1) as u8;
+    fn draw_sprite(&mut self) -> Result<(), Chip8Error> {
+        // Wait for the next frame once this frame's draw allowance is spent
+        if let Some(limit) = self.quirks.draws_per_frame {
+            if self.frame_draws >= limit {
+                self.pc = self.pc.wrapping_sub(2);
+                return Ok(());
+            }
+            self.frame_draws += 1;
+        }
 
-        // Clear VF flag
-        self.reg_v[0xF] = 0;
+        // Extract start coords from registers, wrapped to the active resolution
+        let width: usize = self.width();
+        let height: usize = self.height();
+        let start_x: usize = self.reg_v[self.instr.x()] as usize;
+        let start_y: usize = self.reg_v[self.instr.y()] as usize;
+        let x: usize = start_x % width;
+        let y: usize = start_y % height;
+
+        self.draw_stats.sprites += 1;
+        self.display_dirty = true;
+        if let Some(collisions) = self.collisions.as_mut() {
+            collisions.clear();
+        }
+
+        // Sprites starting off screen are dropped entirely with the start-clip quirk
+        if self.quirks.clip_start && (start_x >= width || start_y >= height) {
+            self.reg_v[0xF] = 0;
+            return Ok(());
+        }
+        let mut collided_rows: u8 = 0;
+        let mut clipped_rows: u8 = 0;
 
         // Populate pixels
+        // Rows past the end of RAM wrap around to 0x000, or fail in strict mode
         for row in 0 .. self.instr.n() {
-            let pixel_blob = self.memory[(self.reg_i + row as u16) as usize];
-            for col in 0 .. 8 {
-                if (pixel_blob & (0x80 >> col)) != 0 {
-                    let px = (x + col) as usize;
-                    let py = (y + row) as usize;
+            let pixel_blob = self.read_mem(self.reg_i.wrapping_add(row as u16))?;
+            let mut row_collided: bool = false;
+            if !self.quirks.wrap_y && y + row as usize >= height {
+                clipped_rows += 1;
+            }
+            for (col, lit) in sprite_row_pixels(pixel_blob).enumerate() {
+                if lit {
+                    let mut px = x + col;
+                    let mut py = y + row as usize;
+                    if self.quirks.wrap_x {
+                        px %= width;
+                    }
+                    if self.quirks.wrap_y {
+                        py %= height;
+                    }
 
-                    if px < SCREEN_WIDTH.into() && py < SCREEN_HEIGHT.into()
+                    if px < width && py < height
                     {
-                        let index = (SCREEN_WIDTH as usize) * py + px;
+                        let index = width * py + px;
                         if self.graphics_buffer[index]
                         {
-                            self.reg_v[0xF] = 1;
+                            row_collided = true;
+                            if let Some(collisions) = self.collisions.as_mut() {
+                                collisions.push(index);
+                            }
+                            if self.draw_mode == DrawMode::Xor {
+                                self.draw_stats.pixels_off += 1;
+                            }
+                        }
+                        else
+                        {
+                            self.draw_stats.pixels_on += 1;
+                        }
+                        match self.draw_mode {
+                            DrawMode::Xor => self.graphics_buffer[index] ^= true,
+                            DrawMode::Overwrite => self.graphics_buffer[index] = true,
                         }
-                        self.graphics_buffer[index] ^= true;
                     }
                 }
             }
+            if row_collided {
+                collided_rows += 1;
+            }
         }
+
+        // Report collisions in VF
+        self.reg_v[0xF] = if self.quirks.vf_row_count {
+            collided_rows + clipped_rows
+        }
+        else {
+            (collided_rows > 0) as u8
+        };
+        Ok(())
     }
 
     /// EX9E: Skip next if Key[VX] pressed
-    fn skip_key_pressed(&mut self) {
-        if self.keypad[self.reg_v[self.instr.x()] as usize] {
-            self.pc += 2;
+    fn skip_key_pressed(&mut self) -> Result<(), Chip8Error> {
+        if self.keypad[self.key_in_vx()?] {
+            self.pc = self.pc.wrapping_add(2);
         }
+        Ok(())
     }
 
     /// EXA1: Skip next if Key[VX] not pressed
-    fn skip_key_not_pressed(&mut self) {
-        if !self.keypad[self.reg_v[self.instr.x()] as usize] {
-            self.pc += 2;
+    fn skip_key_not_pressed(&mut self) -> Result<(), Chip8Error> {
+        if !self.keypad[self.key_in_vx()?] {
+            self.pc = self.pc.wrapping_add(2);
+        }
+        Ok(())
+    }
+
+    /// Gets the key in VX for EX9E and EXA1, using the low nibble of values above 0xF (failing in strict mode).
+    fn key_in_vx(&self) -> Result<usize, Chip8Error> {
+        let key: u8 = self.reg_v[self.instr.x()];
+        if self.strict && key > 0xF {
+            return Err(Chip8Error::InvalidKey(key));
         }
+        Ok((key & 0xF) as usize)
     }
 
     /// FX07: VX = DELAY
@@ -429,65 +2116,1714 @@ impl Chip8 {
 
     /// FX0A: Await key, VX = Key pressed.
     fn await_key(&mut self) {
-        for key in 0x0 ..= 0xF {
-            if self.keypad[key] {
-                self.reg_v[self.instr.x()] = key as u8;
-                return;
-            }
+        // Keys pressed through set_key go first in FirstPress mode, then any set on the keypad directly
+        let first_press: Option<u8> = match self.await_key_mode {
+            AwaitKeyMode::LowestIndex => None,
+            AwaitKeyMode::FirstPress => self.press_order.iter().copied().find(|key| self.keypad[*key as usize]),
+        };
+        match first_press.or_else(|| self.first_pressed_key()) {
+            Some(key) => {
+                self.reg_v[self.instr.x()] = key;
+                self.waiting = false;
+            },
+            None => {
+                self.pc = self.pc.wrapping_sub(2);
+                self.waiting = true;
+            },
         }
-        self.pc -= 2;
     }
 
     /// FX15: DELAY = VX
     fn set_delay(&mut self) {
         self.reg_delay = self.reg_v[self.instr.x()];
+        self.report_delay(DelayEvent::Set);
     }
 
     /// FX18: SOUND = VX
     fn set_sound(&mut self) {
-        self.reg_sound = self.reg_v[self.instr.x()];
+        self.set_sound_timer(self.reg_v[self.instr.x()]);
     }
 
     /// FX1E: I += VX
     fn add_addr(&mut self) {
         self.reg_i = self.reg_i.wrapping_add(self.reg_v[self.instr.x()] as u16);
+        if self.quirks.index_overflow_flag {
+            self.reg_v[0xF] = (self.reg_i > 0x0FFF) as u8;
+        }
     }
 
     /// FX29: I = Font[VX]
-    fn load_digit_addr(&mut self) {
+    fn load_digit_addr(&mut self) -> Result<(), Chip8Error> {
         // Sanity
-        if self.reg_v[self.instr.x()] > 0xF {
-            panic!("Attempted to fetch font digit greater than 0xF.");
+        let digit: u8 = self.reg_v[self.instr.x()];
+        if self.strict && digit > 0xF {
+            return Err(Chip8Error::InvalidFontDigit(digit));
         }
 
-        self.reg_i = FONT_START_ADDRESS + (self.reg_v[self.instr.x()] as u16 * 5);
+        self.reg_i = self.font_address + ((digit & 0xF) as u16 * 5);
+        Ok(())
     }
 
     /// FX33: [I..I+2] = BCD of VX
-    fn move_bcd(&mut self) {
+    fn move_bcd(&mut self) -> Result<(), Chip8Error> {
+        // Write the ones digit first, so strict mode fails at the end of RAM before writing anything
         let mut value: u8 = self.reg_v[self.instr.x()];
-        self.memory[(self.reg_i + 2) as usize] = value % 10;
+        self.write_mem(self.reg_i.wrapping_add(2), value % 10)?;
         value /= 10;
-        self.memory[(self.reg_i + 1) as usize] = value % 10;
+        self.write_mem(self.reg_i.wrapping_add(1), value % 10)?;
         value /= 10;
-        self.memory[self.reg_i as usize] = value;
+        self.write_mem(self.reg_i, value)
     }
 
-    /// FX55: [I..I+X] = [V0..VX]; I += X + 1
-    fn move_regs(&mut self) {
+    /// FX55: [I..=I+X] = [V0..=VX]; I += X + 1 (X + 1 registers, VX included)
+    fn move_regs(&mut self) -> Result<(), Chip8Error> {
         for reg in 0 ..= self.instr.x() {
-            self.memory[self.reg_i as usize + reg] = self.reg_v[reg];
+            self.write_mem(self.reg_i.wrapping_add(reg as u16), self.reg_v[reg])?;
         }
 
-        self.reg_i += self.instr.x() as u16 + 1;
+        if self.quirks.store_increment {
+            self.reg_i = self.reg_i.wrapping_add(self.instr.x() as u16 + 1);
+        }
+        Ok(())
     }
 
-    /// FX65: [V0..VX] = [I..I+X]; I += X + 1
-    fn load_regs(&mut self) {
+    /// FX65: [V0..=VX] = [I..=I+X]; I += X + 1 (X + 1 registers, VX included)
+    fn load_regs(&mut self) -> Result<(), Chip8Error> {
         for reg in 0 ..= self.instr.x() {
-            self.reg_v[reg] = self.memory[self.reg_i as usize + reg];
+            self.reg_v[reg] = self.read_mem(self.reg_i.wrapping_add(reg as u16))?;
+        }
+
+        if self.quirks.load_increment {
+            self.reg_i = self.reg_i.wrapping_add(self.instr.x() as u16 + 1);
+        }
+        Ok(())
+    }
+
+    /// FX75: RPL[0..X] = [V0..VX] (SUPER-CHIP)
+    fn save_flags(&mut self) {
+        let count: usize = self.instr.x() + 1;
+        self.rpl_flags[.. count].copy_from_slice(&self.reg_v[.. count]);
+        if let Some(callback) = self.rpl_callback.as_mut() {
+            callback(&self.rpl_flags);
         }
+    }
+
+    /// FX85: [V0..VX] = RPL[0..X] (SUPER-CHIP)
+    fn load_flags(&mut self) {
+        let count: usize = self.instr.x() + 1;
+        self.reg_v[.. count].copy_from_slice(&self.rpl_flags[.. count]);
+    }
+}
+
+/// Formats one line of a disassembly listing.
+fn listing_line(addr: u16, high: u8, low: u8) -> String {
+    let instr: Instruction = Instruction { raw: (high as u16) << 8 | low as u16 };
+    match instr.disassemble() {
+        Some(text) => format!("{:04X}: {:04X}  {}\n", addr, instr.raw, text),
+        None => format!("{:04X}: {:04X}  ; not an instruction\n", addr, instr.raw),
+    }
+}
+
+/// Splits a sprite row into its 8 pixels, leftmost (most significant bit) first.
+fn sprite_row_pixels(bits: u8) -> impl Iterator<Item = bool> {
+    (0 .. 8).map(move |col| bits & (0x80 >> col) != 0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    impl Chip8 {
+        /// Puts the machine into exactly the state an opcode test needs: the V registers, I, the PC, and bytes
+        /// written at the given addresses (usually the opcode at the PC), ready for `cycle()`.
+        fn with_state(mut self, reg_v: [u8; 16], reg_i: u16, pc: u16, memory: &[(u16, &[u8])]) -> Chip8 {
+            self.reg_v = reg_v;
+            self.reg_i = reg_i;
+            self.pc = pc;
+            for (addr, bytes) in memory.iter() {
+                for (offset, byte) in bytes.iter().enumerate() {
+                    self.memory[*addr as usize + offset] = *byte;
+                }
+            }
+            self
+        }
+    }
+
+    /// Runs a ROM headlessly for a number of cycles and checks the resulting screen against a hash.
+    fn assert_framebuffer_hash(rom: &[u8], cycles: usize, expected: u64) {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_bytes(rom).unwrap();
+        for _ in 0 .. cycles {
+            chip8.cycle().unwrap();
+        }
+
+        let actual: u64 = chip8.framebuffer_hash();
+        assert!(
+            actual == expected,
+            "Framebuffer hash mismatch: expected 0x{:016X}, got 0x{:016X}\n{}",
+            expected,
+            actual,
+            chip8.render_ascii(),
+        );
+    }
+
+    #[test]
+    fn strict_mode_rejects_odd_pc() {
+        let mut chip8: Chip8 = Chip8::builder().strict(true).build();
+        chip8.load_rom_bytes(&[0x12, 0x03]).unwrap();
+
+        assert_eq!(chip8.cycle(), Ok(()));
+        assert_eq!(chip8.pc(), 0x203);
+        assert_eq!(chip8.cycle(), Err(Chip8Error::MisalignedProgramCounter(0x203)));
+    }
+
+    #[test]
+    fn lenient_mode_tolerates_odd_pc() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x12, 0x03, 0x00, 0x60, 0x05]).unwrap();
+
+        assert_eq!(chip8.cycle(), Ok(()));
+        assert_eq!(chip8.cycle(), Ok(()));
+        assert_eq!(chip8.pc(), 0x205);
+        assert_eq!(chip8.reg_v()[0], 0x05);
+    }
+
+    #[test]
+    fn strict_mode_rejects_stack_overflow() {
+        // 0x200: call 0x200, forever
+        let mut strict: Chip8 = Chip8::builder().strict(true).build();
+        strict.load_rom_bytes(&[0x22, 0x00]).unwrap();
+        for _ in 0 .. DEFAULT_STACK_DEPTH {
+            strict.cycle().unwrap();
+        }
+        assert_eq!(strict.cycle(), Err(Chip8Error::StackOverflow));
+
+        let mut lenient: Chip8 = Chip8::new();
+        lenient.load_rom_bytes(&[0x22, 0x00]).unwrap();
+        for _ in 0 ..= DEFAULT_STACK_DEPTH {
+            lenient.cycle().unwrap();
+        }
+        assert_eq!(lenient.sp(), 1);
+    }
+
+    #[test]
+    fn stack_depth_is_configurable() {
+        // 0x200: call 0x200, forever
+        let mut cosmac: Chip8 = Chip8::builder().strict(true).stack_depth(12).build();
+        cosmac.load_rom_bytes(&[0x22, 0x00]).unwrap();
+        for _ in 0 .. 12 {
+            cosmac.cycle().unwrap();
+        }
+        assert_eq!(cosmac.stack().len(), 12);
+        assert_eq!(cosmac.cycle(), Err(Chip8Error::StackOverflow));
+    }
+
+    #[test]
+    fn strict_mode_rejects_unknown_and_machine_code_opcodes() {
+        for opcode in [[0xE0, 0x00], [0x01, 0x23]] {
+            let mut strict: Chip8 = Chip8::builder().strict(true).build();
+            strict.load_rom_bytes(&opcode).unwrap();
+            assert!(strict.cycle().is_err());
+
+            let mut lenient: Chip8 = Chip8::new();
+            lenient.load_rom_bytes(&opcode).unwrap();
+            assert_eq!(lenient.cycle(), Ok(()));
+            assert_eq!(lenient.pc(), 0x202);
+        }
+    }
+
+    #[test]
+    fn strict_mode_rejects_out_of_bounds_memory() {
+        // I = 0xFFF; FX33 writes up to 0x1001
+        let rom: [u8; 4] = [0xAF, 0xFF, 0xF0, 0x33];
+
+        let mut strict: Chip8 = Chip8::builder().strict(true).build();
+        strict.load_rom_bytes(&rom).unwrap();
+        strict.cycle().unwrap();
+        assert_eq!(strict.cycle(), Err(Chip8Error::AddressOutOfBounds(0x1001)));
+
+        let mut lenient: Chip8 = Chip8::new();
+        lenient.load_rom_bytes(&rom).unwrap();
+        lenient.cycle().unwrap();
+        assert_eq!(lenient.cycle(), Ok(()));
+    }
+
+    #[test]
+    fn font_digits_render_to_known_frame() {
+        // Draw "0" at (0, 0) and "8" at (10, 5), then spin
+        let rom: [u8; 18] = [
+            0xA0, 0x50, 0x60, 0x00, 0x61, 0x00, 0xD0, 0x15, 0x60, 0x0A,
+            0x61, 0x05, 0xA0, 0x78, 0xD0, 0x15, 0x12, 0x10,
+        ];
+        assert_framebuffer_hash(&rom, 20, 0x13BB490C64DE63A3);
+    }
+
+    /// Sets the sound timer to `value` and counts the frames the sound callback keeps the buzzer on for.
+    fn count_audible_frames(threshold: u8, value: u8) -> usize {
+        let mut chip8: Chip8 = Chip8::builder().sound_threshold(threshold).build();
+        let playing: Rc<Cell<bool>> = Rc::new(Cell::new(false));
+        let callback_playing: Rc<Cell<bool>> = Rc::clone(&playing);
+        chip8.set_sound_callback(move |audible| callback_playing.set(audible));
+
+        // VX = value; SOUND = VX; spin
+        chip8.load_rom_bytes(&[0x60, value, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+
+        let mut frames: usize = 0;
+        for _ in 0 .. 2 * value as usize + 2 {
+            if playing.get() {
+                frames += 1;
+            }
+            chip8.cycle().unwrap();
+            chip8.tick_timers();
+        }
+        frames
+    }
+
+    #[test]
+    fn sound_threshold_controls_beep_length() {
+        assert_eq!(count_audible_frames(DEFAULT_SOUND_THRESHOLD, 5), 4);
+        assert_eq!(count_audible_frames(0, 5), 5);
+        assert_eq!(count_audible_frames(DEFAULT_SOUND_THRESHOLD, 1), 0);
+        assert_eq!(count_audible_frames(0, 1), 1);
+        assert_eq!(count_audible_frames(2, 2), 0);
+        assert_eq!(count_audible_frames(2, 3), 1);
+    }
+
+    #[test]
+    fn framebuffer_exports_match_resolution() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex("A050 D005").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+
+        let on: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+        let off: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+        let rgba: Vec<u8> = chip8.framebuffer_rgba(on, off);
+        assert_eq!(rgba.len(), chip8.width() * chip8.height() * 4);
+        assert_eq!(rgba[.. 4], on);
+        assert_eq!(rgba[4 * 4 .. 5 * 4], off);
+
+        let ascii: String = chip8.render_ascii();
+        assert_eq!(ascii.lines().count(), chip8.height());
+        assert!(ascii.lines().all(|line| line.len() == chip8.width()));
+    }
+
+    #[test]
+    fn peek_and_poke_are_bounds_checked() {
+        let mut chip8: Chip8 = Chip8::new();
+
+        assert_eq!(chip8.poke(0x300, 0xAB), Ok(()));
+        assert_eq!(chip8.peek(0x300), Ok(0xAB));
+        assert_eq!(chip8.peek(FONT_START_ADDRESS), Ok(0xF0));
+        assert_eq!(chip8.poke(0x1000, 0xAB), Err(Chip8Error::AddressOutOfBounds(0x1000)));
+        assert_eq!(chip8.peek(0xFFFF), Err(Chip8Error::AddressOutOfBounds(0xFFFF)));
+    }
+
+    #[test]
+    fn machine_code_behavior_is_configurable() {
+        let rom: [u8; 4] = [0x01, 0x23, 0x60, 0x01];
+
+        let mut error: Chip8 = Chip8::builder().machine_code(MachineCodeBehavior::Error).build();
+        error.load_rom_bytes(&rom).unwrap();
+        assert_eq!(error.cycle(), Err(Chip8Error::UnsupportedInstruction(0x0123)));
+
+        let mut skip: Chip8 = Chip8::builder().strict(true).machine_code(MachineCodeBehavior::Skip).build();
+        skip.load_rom_bytes(&rom).unwrap();
+        skip.cycle().unwrap();
+        skip.cycle().unwrap();
+        assert_eq!(skip.reg_v()[0], 0x01);
+
+        let mut halt: Chip8 = Chip8::builder().machine_code(MachineCodeBehavior::Halt).build();
+        halt.load_rom_bytes(&rom).unwrap();
+        halt.cycle().unwrap();
+        halt.cycle().unwrap();
+        assert!(halt.is_halted());
+        assert_eq!(halt.pc(), 0x200);
+        assert_eq!(halt.reg_v()[0], 0x00);
+    }
+
+    #[test]
+    fn draws_per_frame_limit_waits_for_next_frame() {
+        let quirks: Quirks = Quirks { draws_per_frame: Some(2), ..Quirks::default() };
+        let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build();
+        // Four draws of the "0" glyph, then loop
+        chip8.load_rom_hex("A050 D005 D005 D005 D005 120A").unwrap();
+
+        for _ in 0 .. 20 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.frame_draws, 2);
+        assert_eq!(chip8.pc(), 0x206);
+
+        chip8.tick_timers();
+        for _ in 0 .. 20 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.frame_draws, 2);
+        assert_eq!(chip8.pc(), 0x20A);
+    }
+
+    #[test]
+    fn dump_state_reports_registers_stack_and_screen() {
+        let mut chip8: Chip8 = Chip8::new();
+        // V3 = #2A, call 0x206, then clear the screen
+        chip8.load_rom_hex("632A 2206 0000 00E0").unwrap();
+        chip8.step_until(|c| c.pc() == 0x208, 10).unwrap();
+
+        let dump: String = chip8.dump_state();
+        let mut lines = dump.lines();
+        assert_eq!(lines.next(), Some("PC=0208 I=0000 OP=00E0 SP=1 DT=0 ST=0"));
+        assert!(lines.next().unwrap().contains("V3=2A"));
+        assert_eq!(lines.next(), Some("STACK=[0204]"));
+        assert_eq!(lines.count(), chip8.height());
+    }
+
+    #[test]
+    fn quirk_changes_apply_mid_run() {
+        let mut chip8: Chip8 = Chip8::new();
+        // V0 = #04, V1 = #10, then V0 >>= 1 twice (via VY, then in place)
+        chip8.load_rom_hex("6004 6110 8016 8016").unwrap();
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.reg_v()[0], 0x08);
+
+        chip8.quirks_mut().shift = true;
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.reg_v()[0], 0x04);
+    }
+
+    #[test]
+    fn sprite_wrapping_is_independent_per_axis() {
+        // Draw the "8" glyph (4x5, 0xF0 0x90 0xF0 0x90 0xF0) at (62, 30)
+        let rom: &str = "603E 611E A078 D015";
+        let lit = |chip8: &Chip8, x: usize, y: usize| chip8.graphics_buffer[y * SCREEN_WIDTH as usize + x];
+
+        for (wrap_x, wrap_y) in [(false, false), (true, false), (false, true), (true, true)] {
+            let quirks: Quirks = Quirks { wrap_x, wrap_y, ..Quirks::default() };
+            let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build();
+            chip8.load_rom_hex(rom).unwrap();
+            for _ in 0 .. 4 {
+                chip8.cycle().unwrap();
+            }
+
+            // The top-left of the sprite is always on screen
+            assert!(lit(&chip8, 62, 30) && lit(&chip8, 63, 30) && lit(&chip8, 62, 31));
+            // Right half of the top row lands on column 0 only when wrapping horizontally
+            assert_eq!(lit(&chip8, 0, 30), wrap_x, "wrap_x={} wrap_y={}", wrap_x, wrap_y);
+            // Third row lands on row 0 only when wrapping vertically
+            assert_eq!(lit(&chip8, 62, 0), wrap_y, "wrap_x={} wrap_y={}", wrap_x, wrap_y);
+            // The bottom-right corner needs both
+            assert_eq!(lit(&chip8, 1, 0), wrap_x && wrap_y, "wrap_x={} wrap_y={}", wrap_x, wrap_y);
+        }
+    }
+
+    #[test]
+    fn sprite_start_wraps_or_clips() {
+        // Draw the "0" glyph (0xF0 0x90 0x90 0x90 0xF0) at (70, 0), past the right edge
+        let rom: &str = "6046 6100 A050 D015";
+        let lit = |chip8: &Chip8, x: usize, y: usize| chip8.graphics_buffer[y * SCREEN_WIDTH as usize + x];
+
+        for clip_start in [false, true] {
+            let quirks: Quirks = Quirks { clip_start, ..Quirks::default() };
+            let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build();
+            chip8.load_rom_hex(rom).unwrap();
+            for _ in 0 .. 4 {
+                chip8.cycle().unwrap();
+            }
+
+            // Wrapping the start puts the sprite at column 6, clipping leaves the screen blank
+            assert_eq!(lit(&chip8, 6, 0), !clip_start, "clip_start={}", clip_start);
+            assert_eq!(lit(&chip8, 9, 4), !clip_start, "clip_start={}", clip_start);
+            assert_eq!(chip8.graphics_buffer.iter().any(|pixel| *pixel), !clip_start, "clip_start={}", clip_start);
+            assert_eq!(chip8.reg_v()[0xF], 0);
+        }
+
+        // A start on screen draws the same either way
+        let quirks: Quirks = Quirks { clip_start: true, ..Quirks::default() };
+        let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build();
+        chip8.load_rom_hex("603C 6100 A050 D015").unwrap();
+        for _ in 0 .. 4 {
+            chip8.cycle().unwrap();
+        }
+        assert!(lit(&chip8, 60, 0) && lit(&chip8, 63, 4));
+    }
+
+    #[test]
+    fn protected_font_rejects_writes() {
+        // I = 0x55, V0 = #FF, store V0
+        let rom: &str = "A055 60FF F055";
+
+        let mut strict: Chip8 = Chip8::builder().strict(true).protect_font(true).build();
+        strict.load_rom_hex(rom).unwrap();
+        strict.cycle().unwrap();
+        strict.cycle().unwrap();
+        assert_eq!(strict.cycle(), Err(Chip8Error::ProtectedWrite(0x55)));
+        assert_eq!(strict.peek(0x55), Ok(FONT_DATA[5]));
+
+        let mut lenient: Chip8 = Chip8::builder().protect_font(true).build();
+        lenient.load_rom_hex(rom).unwrap();
+        for _ in 0 .. 3 {
+            lenient.cycle().unwrap();
+        }
+        assert_eq!(lenient.peek(0x55), Ok(FONT_DATA[5]));
+    }
+
+    #[test]
+    fn self_jump_is_detected_as_idle() {
+        // V0 = #01, then jump to self
+        let rom: &str = "6001 1202";
+
+        let mut detecting: Chip8 = Chip8::builder().detect_idle(true).build();
+        detecting.load_rom_hex(rom).unwrap();
+        detecting.cycle().unwrap();
+        assert_eq!(detecting.status(), Status::Running);
+        detecting.cycle().unwrap();
+        assert_eq!(detecting.status(), Status::Idle);
+        assert_eq!(detecting.pc(), 0x202);
+
+        let mut spinning: Chip8 = Chip8::new();
+        spinning.load_rom_hex(rom).unwrap();
+        for _ in 0 .. 10 {
+            spinning.cycle().unwrap();
+        }
+        assert_eq!(spinning.status(), Status::Running);
+    }
+
+    #[test]
+    fn draw_stats_count_pixels_per_frame() {
+        let mut chip8: Chip8 = Chip8::new();
+        // Draw the "0" glyph twice: 14 pixels on, then the same 14 off
+        chip8.load_rom_hex("A050 D005 D005").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.draw_stats(), DrawStats { sprites: 1, pixels_on: 14, pixels_off: 0 });
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.draw_stats(), DrawStats { sprites: 2, pixels_on: 14, pixels_off: 14 });
+
+        chip8.tick_timers();
+        assert_eq!(chip8.draw_stats(), DrawStats::default());
+    }
+
+    #[test]
+    fn vf_row_count_reports_collided_and_clipped_rows() {
+        let quirks: Quirks = Quirks { vf_row_count: true, ..Quirks::default() };
+
+        // Draw the "0" glyph twice at the same spot: all 5 rows collide
+        let mut counting: Chip8 = Chip8::builder().quirks(quirks).build();
+        counting.load_rom_hex("A050 D005 D005").unwrap();
+        for _ in 0 .. 3 {
+            counting.cycle().unwrap();
+        }
+        assert_eq!(counting.reg_v()[0xF], 5);
+
+        // Draw the "0" glyph over the "1" glyph (20 60 20 20 70): only the top and bottom rows overlap
+        let mut partial: Chip8 = Chip8::builder().quirks(quirks).build();
+        partial.load_rom_hex("A055 D005 A050 D005").unwrap();
+        for _ in 0 .. 4 {
+            partial.cycle().unwrap();
+        }
+        assert_eq!(partial.reg_v()[0xF], 2);
+
+        // Draw the "0" glyph at y = 30 on a blank screen: 3 rows fall off the bottom
+        let mut clipped: Chip8 = Chip8::builder().quirks(quirks).build();
+        clipped.load_rom_hex("611E A050 D015").unwrap();
+        for _ in 0 .. 3 {
+            clipped.cycle().unwrap();
+        }
+        assert_eq!(clipped.reg_v()[0xF], 3);
+
+        // The default reports any collision as 1, and ignores clipping
+        let mut plain: Chip8 = Chip8::new();
+        plain.load_rom_hex("A050 D005 D005 611E D015").unwrap();
+        for _ in 0 .. 3 {
+            plain.cycle().unwrap();
+        }
+        assert_eq!(plain.reg_v()[0xF], 1);
+        plain.cycle().unwrap();
+        plain.cycle().unwrap();
+        assert_eq!(plain.reg_v()[0xF], 0);
+    }
+
+    #[test]
+    fn first_pressed_key_is_lowest_held_key() {
+        let mut chip8: Chip8 = Chip8::new();
+        assert_eq!(chip8.first_pressed_key(), None);
+
+        chip8.keypad[0xB] = true;
+        chip8.keypad[0x4] = true;
+        assert_eq!(chip8.first_pressed_key(), Some(0x4));
+    }
+
+    #[test]
+    fn set_program_counter_is_guarded() {
+        let mut chip8: Chip8 = Chip8::builder().detect_idle(true).build();
+        // Spin at 0x200; a routine setting V0 = #07 sits at 0x202
+        chip8.load_rom_hex("1200 6007").unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.status(), Status::Idle);
+
+        assert_eq!(chip8.set_program_counter(0x203), Err(Chip8Error::MisalignedProgramCounter(0x203)));
+        assert_eq!(chip8.set_program_counter(0xFFF), Err(Chip8Error::AddressOutOfBounds(0xFFF)));
+        assert_eq!(chip8.set_program_counter(0x1000), Err(Chip8Error::AddressOutOfBounds(0x1000)));
+        assert_eq!(chip8.pc(), 0x200);
+
+        chip8.set_program_counter(0x202).unwrap();
+        assert_eq!(chip8.status(), Status::Running);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.reg_v()[0], 0x07);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn coverage_tracks_executed_and_missed_opcodes() {
+        let mut chip8: Chip8 = Chip8::new();
+        // V0 = #01, skip the V1 load, V2 = #03, then spin
+        chip8.load_rom_hex("6001 1206 6102 6203 1208").unwrap();
+        for _ in 0 .. 6 {
+            chip8.cycle().unwrap();
+        }
+
+        let expected: HashSet<u16> = HashSet::from([0x6001, 0x1206, 0x6203, 0x1208]);
+        assert_eq!(chip8.executed_opcodes(), &expected);
+        assert_eq!(
+            chip8.coverage_report(),
+            "Executed (4): 1206 1208 6001 6203\nNever executed (1): 6102\n"
+        );
+
+        chip8.reset();
+        assert!(chip8.executed_opcodes().is_empty());
+    }
+
+    #[test]
+    fn disassembly_lists_the_loaded_rom() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x00, 0xE0, 0x6A, 0x02, 0xD0, 0x15, 0xFF, 0xFF, 0x12]).unwrap();
+
+        assert_eq!(
+            chip8.disassembly(),
+            "0200: 00E0  CLS\n\
+             0202: 6A02  LD VA, #02\n\
+             0204: D015  DRW V0, V1, 5\n\
+             0206: FFFF  ; not an instruction\n\
+             0208: 12    ; trailing byte\n"
+        );
+        assert_eq!(chip8.disassemble(0x202, 2), Ok("0202: 6A02  LD VA, #02\n0204: D015  DRW V0, V1, 5\n".to_string()));
+        assert_eq!(chip8.disassemble(0xFFE, 2), Err(Chip8Error::AddressOutOfBounds(0x1000)));
+    }
+
+    #[test]
+    fn index_overflow_flag_is_configurable() {
+        // V0 = #01, VF = #07, I += V0 twice from 0xFFE
+        let mut reg_v: [u8; 16] = [0; 16];
+        reg_v[0x0] = 0x01;
+        reg_v[0xF] = 0x07;
+        let code: &[(u16, &[u8])] = &[(0x200, &[0xF0, 0x1E, 0xF0, 0x1E])];
+
+        let quirks: Quirks = Quirks { index_overflow_flag: true, ..Quirks::default() };
+        let mut flagged: Chip8 = Chip8::builder().quirks(quirks).build().with_state(reg_v, 0xFFE, 0x200, code);
+        flagged.cycle().unwrap();
+        assert_eq!((flagged.reg_i(), flagged.reg_v()[0xF]), (0x0FFF, 0));
+        flagged.cycle().unwrap();
+        assert_eq!((flagged.reg_i(), flagged.reg_v()[0xF]), (0x1000, 1));
+
+        let mut plain: Chip8 = Chip8::new().with_state(reg_v, 0xFFE, 0x200, code);
+        plain.cycle().unwrap();
+        plain.cycle().unwrap();
+        assert_eq!((plain.reg_i(), plain.reg_v()[0xF]), (0x1000, 0x07));
+    }
+
+    #[test]
+    fn sprite_start_wraps_at_resolution_boundary() {
+        // Only the 64x32 resolution exists so far; coordinates of 64 and 32 start back at the edge
+        for (vx, vy, expected) in [(63, 31, (63, 31)), (64, 32, (0, 0)), (0x7F, 0x3F, (63, 31))] {
+            let mut chip8: Chip8 = Chip8::new();
+            // Draw the top row of the "0" glyph (0xF0) at (V0, V1)
+            chip8.load_rom_bytes(&[0x60, vx, 0x61, vy, 0xA0, 0x50, 0xD0, 0x11]).unwrap();
+            for _ in 0 .. 4 {
+                chip8.cycle().unwrap();
+            }
+
+            let lit: Vec<usize> = (0 .. chip8.graphics_buffer.len()).filter(|i| chip8.graphics_buffer[*i]).collect();
+            assert_eq!(lit[0], expected.1 * chip8.width() + expected.0, "V0={} V1={}", vx, vy);
+        }
+    }
+
+    #[test]
+    fn store_and_load_increment_are_independent() {
+        // I = 0x300, store V0-V1, then load V0-V1
+        let rom: &str = "A300 F155 F165";
+        for (store_increment, load_increment) in [(true, false), (false, true)] {
+            let quirks: Quirks = Quirks { store_increment, load_increment, ..Quirks::default() };
+            let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build();
+            chip8.load_rom_hex(rom).unwrap();
+
+            chip8.cycle().unwrap();
+            chip8.cycle().unwrap();
+            let after_store: u16 = if store_increment { 0x302 } else { 0x300 };
+            assert_eq!(chip8.reg_i(), after_store);
+            chip8.cycle().unwrap();
+            assert_eq!(chip8.reg_i(), if load_increment { after_store + 2 } else { after_store });
+        }
+    }
+
+    #[test]
+    fn rng_sequence_feeds_cxnn() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.set_rng_sequence(vec![0xAB, 0x3C]);
+        // V0 = rand & #0F, V1 = rand & #FF, V2 = rand & #F0
+        chip8.load_rom_hex("C00F C1FF C2F0").unwrap();
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(&chip8.reg_v()[.. 3], &[0xAB & 0x0F, 0x3C, 0xAB & 0xF0]);
+    }
+
+    #[test]
+    fn copy_framebuffer_packs_bytes_and_bits() {
+        let mut chip8: Chip8 = Chip8::new();
+        // Draw the top row of the "0" glyph (0xF0) at (2, 1)
+        chip8.load_rom_hex("6002 6101 A050 D011").unwrap();
+        for _ in 0 .. 4 {
+            chip8.cycle().unwrap();
+        }
+        let width: usize = chip8.width();
+
+        let mut bytes: Vec<u8> = vec![0xFF; width * chip8.height()];
+        chip8.copy_framebuffer_into(&mut bytes).unwrap();
+        assert_eq!(&bytes[width .. width + 8], &[0, 0, 1, 1, 1, 1, 0, 0]);
+        assert_eq!(bytes.iter().map(|b| *b as usize).sum::<usize>(), 4);
+
+        let mut bits: Vec<u8> = vec![0xFF; width * chip8.height() / 8];
+        chip8.copy_framebuffer_into(&mut bits).unwrap();
+        assert_eq!(bits[width / 8], 0b0011_1100);
+        assert_eq!(bits.iter().map(|b| b.count_ones()).sum::<u32>(), 4);
+
+        assert_eq!(chip8.copy_framebuffer_into(&mut [0; 7]), Err(Chip8Error::InvalidBufferLength(7)));
+    }
+
+    #[test]
+    fn overwrite_draw_mode_never_erases() {
+        let mut chip8: Chip8 = Chip8::builder().draw_mode(DrawMode::Overwrite).build();
+        // Draw the "0" glyph twice: the second draw leaves it lit but still reports the overlap
+        chip8.load_rom_hex("A050 D005 D005").unwrap();
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert!(chip8.graphics_buffer[0]);
+        assert_eq!(chip8.graphics_buffer.iter().filter(|pixel| **pixel).count(), 14);
+        assert_eq!(chip8.reg_v()[0xF], 1);
+        assert_eq!(chip8.draw_stats(), DrawStats { sprites: 2, pixels_on: 14, pixels_off: 0 });
+    }
+
+    #[test]
+    fn display_dirty_tracks_screen_changes() {
+        let mut chip8: Chip8 = Chip8::new();
+        assert!(chip8.take_display_dirty());
+        assert!(!chip8.take_display_dirty());
+
+        chip8.load_rom_hex("6001 A050 D005 00E0").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert!(!chip8.take_display_dirty());
+        chip8.cycle().unwrap();
+        assert!(chip8.take_display_dirty());
+        assert!(!chip8.take_display_dirty());
+        chip8.cycle().unwrap();
+        assert!(chip8.take_display_dirty());
+    }
+
+    #[test]
+    fn undo_step_reverses_instructions() {
+        let mut chip8: Chip8 = Chip8::builder().undo_depth(8).build();
+        // Set V0, store its BCD, call a subroutine that draws "0" and clears the screen
+        chip8.load_rom_hex("607B A300 F033 220A 0000 A050 D005 00E0").unwrap();
+        for _ in 0 .. 7 {
+            chip8.cycle().unwrap();
+        }
+        let fresh: Chip8 = {
+            let mut fresh: Chip8 = Chip8::new();
+            fresh.load_rom_hex("607B A300 F033 220A 0000 A050 D005 00E0").unwrap();
+            fresh
+        };
+
+        // Undo the clear and the draw: the glyph comes back, then goes away
+        assert_eq!(chip8.undo_step().unwrap().opcode(), 0x00E0);
+        assert_eq!(chip8.graphics_buffer.iter().filter(|pixel| **pixel).count(), 14);
+        assert_eq!(chip8.undo_step().unwrap().opcode(), 0xD005);
+        assert!(chip8.graphics_buffer.iter().all(|pixel| !*pixel));
+
+        // Undo the rest and compare against a machine that never ran
+        for _ in 0 .. 5 {
+            chip8.undo_step().unwrap();
+        }
+        assert!(chip8.undo_step().is_none());
+        assert_eq!(chip8.pc(), 0x200);
+        assert_eq!(chip8.dump_state(), fresh.dump_state());
+        assert_eq!(chip8.peek(0x300), Ok(0));
+
+        // Stepping again replays the same way
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!([chip8.peek(0x300), chip8.peek(0x301), chip8.peek(0x302)], [Ok(1), Ok(2), Ok(3)]);
+    }
+
+    #[test]
+    fn undo_log_is_bounded_and_flags_random_numbers() {
+        let mut chip8: Chip8 = Chip8::builder().undo_depth(2).build();
+        chip8.load_rom_hex("6001 6102 C0FF").unwrap();
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert!(!chip8.undo_step().unwrap().is_repeatable());
+        assert!(chip8.undo_step().unwrap().is_repeatable());
+        assert!(chip8.undo_step().is_none());
+        assert_eq!(&chip8.reg_v()[.. 2], &[1, 0]);
+
+        // Nothing is recorded by default
+        let mut plain: Chip8 = Chip8::new();
+        plain.load_rom_hex("6001").unwrap();
+        plain.cycle().unwrap();
+        assert!(plain.undo_step().is_none());
+    }
+
+    #[test]
+    fn sprite_rows_past_end_of_memory_wrap_or_fail() {
+        // A 3-row sprite at I = 0xFFE reads 0xFFE, 0xFFF, then 0x000
+        let rom: &str = "AFFE D003";
+        let mut wrapping: Chip8 = Chip8::new();
+        wrapping.load_rom_hex(rom).unwrap();
+        wrapping.poke(0xFFE, 0x80).unwrap();
+        wrapping.poke(0xFFF, 0x40).unwrap();
+        wrapping.poke(0x000, 0x20).unwrap();
+        wrapping.cycle().unwrap();
+        wrapping.cycle().unwrap();
+        let width: usize = wrapping.width();
+        assert!(wrapping.graphics_buffer[0]);
+        assert!(wrapping.graphics_buffer[width + 1]);
+        assert!(wrapping.graphics_buffer[2 * width + 2]);
+        assert_eq!(wrapping.graphics_buffer.iter().filter(|pixel| **pixel).count(), 3);
+
+        let mut strict: Chip8 = Chip8::builder().strict(true).build();
+        strict.load_rom_hex(rom).unwrap();
+        strict.cycle().unwrap();
+        assert_eq!(strict.cycle(), Err(Chip8Error::AddressOutOfBounds(0x1000)));
+    }
+
+    #[test]
+    fn shift_quirk_leaves_vy_untouched() {
+        let quirks: Quirks = Quirks { shift: true, ..Quirks::default() };
+        // V0 = #81, V1 = #42, then V0 >>= 1 and V0 <<= 1 with Y = 1
+        let mut reg_v: [u8; 16] = [0; 16];
+        reg_v[0] = 0x81;
+        reg_v[1] = 0x42;
+        let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build()
+            .with_state(reg_v, 0, 0x200, &[(0x200, &[0x80, 0x16, 0x80, 0x1E])]);
+        chip8.cycle().unwrap();
+        assert_eq!(&chip8.reg_v()[.. 2], &[0x40, 0x42]);
+        assert_eq!(chip8.reg_v()[0xF], 1);
+
+        chip8.cycle().unwrap();
+        assert_eq!(&chip8.reg_v()[.. 2], &[0x80, 0x42]);
+        assert_eq!(chip8.reg_v()[0xF], 0);
+    }
+
+    #[test]
+    fn alu_callback_reports_operands_and_flags() {
+        let events: Rc<RefCell<Vec<AluEvent>>> = Rc::new(RefCell::new(Vec::new()));
+        let mut chip8: Chip8 = Chip8::new();
+        let sink: Rc<RefCell<Vec<AluEvent>>> = Rc::clone(&events);
+        chip8.set_alu_callback(move |event| sink.borrow_mut().push(*event));
+
+        // V0 = #0F, V1 = #F1, V0 += V1 (carry, half carry), V0 -= V1 (borrow), V0 += #01
+        chip8.load_rom_hex("600F 61F1 8014 8015 7001").unwrap();
+        for _ in 0 .. 5 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(*events.borrow(), vec![
+            AluEvent { op: AluOp::Add, left: 0x0F, right: 0xF1, result: 0x00, flag: Some(1), half_carry: true },
+            AluEvent { op: AluOp::Sub, left: 0x00, right: 0xF1, result: 0x0F, flag: Some(0), half_carry: true },
+            AluEvent { op: AluOp::AddImmediate, left: 0x0F, right: 0x01, result: 0x10, flag: None, half_carry: true },
+        ]);
+    }
+
+    #[test]
+    fn bcd_at_end_of_memory_wraps_or_fails() {
+        // V0 = 123 stored at I = 0xFFE: the ones digit wraps around to 0x000
+        let mut reg_v: [u8; 16] = [0; 16];
+        reg_v[0] = 123;
+        let code: &[(u16, &[u8])] = &[(0x200, &[0xF0, 0x33])];
+        let mut wrapping: Chip8 = Chip8::new().with_state(reg_v, 0xFFE, 0x200, code);
+        wrapping.cycle().unwrap();
+        assert_eq!([wrapping.peek(0xFFE), wrapping.peek(0xFFF), wrapping.peek(0x000)], [Ok(1), Ok(2), Ok(3)]);
+
+        // Strict mode fails without writing any digits
+        let mut strict: Chip8 = Chip8::builder().strict(true).build().with_state(reg_v, 0xFFE, 0x200, code);
+        assert_eq!(strict.cycle(), Err(Chip8Error::AddressOutOfBounds(0x1000)));
+        assert_eq!([strict.peek(0xFFE), strict.peek(0xFFF)], [Ok(0), Ok(0)]);
+    }
+
+    #[test]
+    fn last_instruction_and_mnemonic() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex("6A0F 8AB9").unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.last_instruction(), 0x6A0F);
+        assert_eq!(chip8.last_mnemonic().as_deref(), Some("LD VA, #0F"));
+
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.last_instruction(), 0x8AB9);
+        assert_eq!(chip8.last_mnemonic(), None);
+    }
+
+    #[test]
+    fn sprite_preview_renders_memory_at_i() {
+        let mut chip8: Chip8 = Chip8::new();
+        // Point I at the "1" glyph
+        chip8.load_rom_hex("A055").unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.sprite_at_i(5), Ok(vec![0x20, 0x60, 0x20, 0x20, 0x70]));
+        let preview: String = chip8.sprite_preview(2).unwrap();
+        assert_eq!(preview, "0055: 20  ..#.....\n0056: 60  .##.....\n");
+        assert!(chip8.graphics_buffer.iter().all(|pixel| !*pixel));
+    }
+
+    #[test]
+    fn screen_sprites_read_back_drawn_glyphs() {
+        let mut chip8: Chip8 = Chip8::new();
+        // Draw the "1" glyph at (0, 0) and the "0" glyph at (10, 2)
+        chip8.load_rom_hex("A055 D005 600A 6102 A050 D015").unwrap();
+        for _ in 0 .. 6 {
+            chip8.cycle().unwrap();
+        }
+
+        assert_eq!(chip8.screen_sprites(0, 0, 8, 5), vec![vec![0x20, 0x60, 0x20, 0x20, 0x70]]);
+        assert_eq!(chip8.screen_sprites(10, 2, 4, 5), vec![vec![0xF0, 0x90, 0x90, 0x90, 0xF0]]);
+        // A wider region splits into strips, and the width cuts off pixels past it
+        assert_eq!(chip8.screen_sprites(2, 2, 10, 2), vec![vec![0x80, 0x80], vec![0xC0, 0x80]]);
+        // Pixels past the screen read as off
+        assert_eq!(chip8.screen_sprites(60, 30, 8, 4), vec![vec![0x00; 4]]);
+        // Regions far off the screen or larger than it don't overflow, and are cut down to the screen size
+        assert_eq!(chip8.screen_sprites(usize::MAX, usize::MAX, 8, 2), vec![vec![0x00; 2]]);
+        let huge: Vec<Vec<u8>> = chip8.screen_sprites(0, 0, usize::MAX, usize::MAX);
+        assert_eq!(huge.len(), 8);
+        assert_eq!(huge[0][.. 5], [0x20, 0x60, 0x20, 0x20, 0x70]);
+        assert!(huge.iter().all(|strip: &Vec<u8>| strip.len() == 32));
+    }
+
+    #[test]
+    fn extended_opcodes_skip_and_copy_ranges() {
+        let mut chip8: Chip8 = Chip8::builder().extended_opcodes(true).build();
+        // V1 = 5, V2 = 3, V3 = 9; skip if V1 > V2; store V1..V3 at 0x300, clear V1, load V1..V2 back
+        chip8.load_rom_hex("6105 6203 6309 5121 0000 A300 5132 6100 5123").unwrap();
+        for _ in 0 .. 4 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.pc(), 0x20A);
+
+        for _ in 0 .. 4 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!([chip8.peek(0x300), chip8.peek(0x301), chip8.peek(0x302)], [Ok(5), Ok(3), Ok(9)]);
+        assert_eq!(&chip8.reg_v()[1 .. 4], &[5, 3, 9]);
+        assert_eq!(chip8.reg_i(), 0x300);
+
+        // Without the flag they're unknown opcodes
+        let mut plain: Chip8 = Chip8::builder().strict(true).build();
+        plain.load_rom_hex("5121").unwrap();
+        assert_eq!(plain.cycle(), Err(Chip8Error::UnknownInstruction(0x5121)));
+    }
+
+    #[test]
+    fn skip_equal_rejects_nonzero_subtype() {
+        // VA and VB are both 0, so a 5XY0 here would skip
+        let mut strict: Chip8 = Chip8::builder().strict(true).build();
+        strict.load_rom_hex("5AB7").unwrap();
+        assert_eq!(strict.cycle(), Err(Chip8Error::UnknownInstruction(0x5AB7)));
+
+        let mut lenient: Chip8 = Chip8::new();
+        lenient.load_rom_hex("5AB7").unwrap();
+        lenient.cycle().unwrap();
+        assert_eq!(lenient.pc(), 0x202);
+    }
+
+    #[test]
+    fn skip_not_equal_rejects_nonzero_subtype() {
+        // VA is 1 and VB is 0, so a 9XY0 here would skip
+        let mut strict: Chip8 = Chip8::builder().strict(true).build();
+        strict.load_rom_hex("6A01 9AB1").unwrap();
+        strict.cycle().unwrap();
+        assert_eq!(strict.cycle(), Err(Chip8Error::UnknownInstruction(0x9AB1)));
+        assert!(!strict.disassemble(0x202, 1).unwrap().contains("SNE"));
+
+        let mut lenient: Chip8 = Chip8::new();
+        lenient.load_rom_hex("6A01 9AB1").unwrap();
+        lenient.cycle().unwrap();
+        lenient.cycle().unwrap();
+        assert_eq!(lenient.pc(), 0x204);
+    }
+
+    #[test]
+    fn range_opcodes_follow_register_order() {
+        let mut chip8: Chip8 = Chip8::builder().extended_opcodes(true).build();
+        // V1..V3 = 1, 2, 3; store ascending at 0x300 and descending (V3..V1) at 0x310,
+        // then load 3, 2, 1 from 0x310 ascending into V4..V6 and descending into V9..V7
+        chip8.load_rom_hex("6101 6202 6303 A300 5132 A310 5312 5463 5973").unwrap();
+        for _ in 0 .. 9 {
+            chip8.cycle().unwrap();
+        }
+        let stored: Vec<u8> = [0x300, 0x301, 0x302, 0x310, 0x311, 0x312].iter().map(|&addr| chip8.peek(addr).unwrap()).collect();
+        assert_eq!(stored, vec![1, 2, 3, 3, 2, 1]);
+        assert_eq!(&chip8.reg_v()[4 .. 10], &[3, 2, 1, 1, 2, 3]);
+        assert_eq!(chip8.reg_i(), 0x310);
+    }
+
+    #[test]
+    fn unknown_handler_runs_custom_opcodes() {
+        let mut chip8: Chip8 = Chip8::builder().strict(true).build();
+        // Prototype FX75 as VX = VX * 2, and leave everything else unknown
+        chip8.set_unknown_handler(|chip8, opcode| {
+            if opcode & 0xF0FF != 0xF075 {
+                return false;
+            }
+            let x: usize = ((opcode >> 8) & 0xF) as usize;
+            chip8.reg_v_mut()[x] = chip8.reg_v()[x].wrapping_mul(2);
+            true
+        });
+        chip8.load_rom_hex("6315 F375 F376").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.reg_v()[3], 0x2A);
+        assert_eq!(chip8.cycle(), Err(Chip8Error::UnknownInstruction(0xF376)));
+    }
+
+    #[test]
+    fn unknown_handler_registers_are_undone_but_memory_is_not() {
+        let mut chip8: Chip8 = Chip8::builder().undo_depth(4).build();
+        chip8.set_unknown_handler(|chip8, _| {
+            chip8.reg_v_mut()[3] = 7;
+            chip8.poke(0x300, 0xAB).unwrap();
+            true
+        });
+        chip8.load_rom_hex("6315 A123 F376").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+
+        let step: StepResult = chip8.step_traced().unwrap().unwrap();
+        assert_eq!(step.changed_registers, vec![(3, 0x15, 7)]);
+        assert!(step.changed_memory.is_empty());
+
+        chip8.undo_step().unwrap();
+        assert_eq!((chip8.pc(), chip8.reg_i(), chip8.reg_v()[3]), (0x204, 0x123, 0x15));
+        assert_eq!(chip8.peek(0x300), Ok(0xAB));
+    }
+
+    #[test]
+    fn run_frame_reports_waiting_for_key() {
+        let mut chip8: Chip8 = Chip8::new();
+        // Set the delay timer, wait for a key into V1, then spin
+        chip8.load_rom_hex("6005 F015 F10A 1206").unwrap();
+        assert_eq!(chip8.run_frame(10), Ok(Status::Waiting));
+        assert_eq!(chip8.pc(), 0x204);
+        assert_eq!(chip8.delay_timer(), 4);
+
+        chip8.keypad[0x7] = true;
+        assert_eq!(chip8.run_frame(10), Ok(Status::Running));
+        assert_eq!(chip8.reg_v()[1], 0x7);
+        assert_eq!(chip8.delay_timer(), 3);
+    }
+
+    #[test]
+    fn draw_zero_rows_near_end_of_memory() {
+        // DXY0 is a 16x16 sprite on SUPER-CHIP; here it draws nothing and reads no memory
+        for strict in [false, true] {
+            let mut chip8: Chip8 = Chip8::builder().strict(strict).build();
+            chip8.load_rom_hex("AFF0 D000").unwrap();
+            chip8.cycle().unwrap();
+            chip8.cycle().unwrap();
+            assert!(chip8.graphics_buffer.iter().all(|lit| !lit));
+            assert_eq!(chip8.reg_v()[0xF], 0);
+        }
+    }
+
+    #[test]
+    fn rom_info_guesses_variant() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex("00E0 6005 D015 1206").unwrap();
+        let info: String = chip8.rom_info();
+        assert!(info.contains("Size:\t\t8 bytes"));
+        assert!(info.contains("Instructions:\tCLS DRW JP LD"));
+        assert!(info.contains("Variant:\tCHIP-8 "));
+
+        chip8.load_rom_hex("00FF D010").unwrap();
+        assert!(chip8.rom_info().contains("Variant:\tSUPER-CHIP "));
+
+        chip8.load_rom_hex("00FF F000 0300").unwrap();
+        assert!(chip8.rom_info().contains("Variant:\tXO-CHIP "));
+    }
+
+    #[test]
+    fn delay_callback_reports_set_and_expiry() {
+        let events: Rc<RefCell<Vec<DelayEvent>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink: Rc<RefCell<Vec<DelayEvent>>> = Rc::clone(&events);
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.set_delay_callback(move |event| sink.borrow_mut().push(event));
+        chip8.load_rom_hex("6002 F015").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        for _ in 0 .. 3 {
+            chip8.tick_timers();
+        }
+        assert_eq!(*events.borrow(), vec![DelayEvent::Set, DelayEvent::Expired]);
+    }
+
+    #[test]
+    fn collision_map_lists_overlapping_pixels() {
+        let mut chip8: Chip8 = Chip8::builder().record_collisions(true).build();
+        // Draw the "0" glyph at (0, 0), then again one pixel right and one down
+        chip8.load_rom_hex("6000 F029 D005 6101 D115").unwrap();
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert!(chip8.last_collisions().is_empty());
+
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        // 0 is F0 90 90 90 F0, so the copy's top edge crosses the right side at (3, 1) and its left side
+        // crosses the bottom edge at (1, 4)
+        let width: usize = chip8.width();
+        assert_eq!(chip8.last_collisions(), &[width + 3, 4 * width + 1]);
+        assert_eq!(chip8.reg_v()[0xF], 1);
+
+        // Off by default
+        let mut plain: Chip8 = Chip8::new();
+        plain.load_rom_hex("F029 D005 D005").unwrap();
+        for _ in 0 .. 3 {
+            plain.cycle().unwrap();
+        }
+        assert!(plain.last_collisions().is_empty());
+    }
+
+    #[test]
+    fn font_can_be_relocated() {
+        let mut chip8: Chip8 = Chip8::builder().font_address(0x000).build();
+        assert_eq!(chip8.peek(0x000), Ok(0xF0));
+        assert_eq!(chip8.peek(FONT_START_ADDRESS), Ok(0x00));
+
+        // FX29 for digit 2 points 10 bytes into the relocated font
+        chip8.load_rom_hex("6002 F029").unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.reg_i(), 0x00A);
+
+        // The font is kept clear of the program
+        let high: Chip8 = Chip8::builder().font_address(0x1E0).build();
+        assert_eq!(high.peek(MAX_FONT_ADDRESS), Ok(0xF0));
+        assert_eq!(high.peek(PC_START_ADDRESS - 1), Ok(0x80));
+    }
+
+    /// Builds V register values from (register, value) pairs, leaving the rest 0.
+    fn regs(values: &[(usize, u8)]) -> [u8; 16] {
+        let mut reg_v: [u8; 16] = [0; 16];
+        for (reg, value) in values.iter() {
+            reg_v[*reg] = *value;
+        }
+        reg_v
+    }
+
+    /// Runs one opcode at 0x200 from the given V registers and I, returning the machine afterwards.
+    fn run_opcode(chip8: Chip8, reg_v: [u8; 16], reg_i: u16, opcode: u16) -> Chip8 {
+        let mut chip8: Chip8 = chip8.with_state(reg_v, reg_i, 0x200, &[(0x200, &opcode.to_be_bytes())]);
+        chip8.cycle().unwrap();
+        chip8
+    }
+
+    #[test]
+    fn opcode_00e0_clears_the_screen() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.graphics_buffer[0] = true;
+        let last: usize = chip8.graphics_buffer.len() - 1;
+        chip8.graphics_buffer[last] = true;
+        let chip8: Chip8 = run_opcode(chip8, regs(&[]), 0, 0x00E0);
+        assert!(chip8.graphics_buffer.iter().all(|lit| !lit));
+        assert_eq!(chip8.pc(), 0x202);
+    }
+
+    #[test]
+    fn opcodes_2nnn_and_00ee_call_and_return() {
+        let mut chip8: Chip8 = Chip8::new().with_state(regs(&[]), 0, 0x200, &[(0x200, &[0x23, 0x00]), (0x300, &[0x00, 0xEE])]);
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.pc(), chip8.sp(), chip8.stack()[0]), (0x300, 1, 0x202));
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.pc(), chip8.sp()), (0x202, 0));
+    }
+
+    #[test]
+    fn nested_calls_return_to_the_instruction_after_each_call() {
+        // Call 0x210, which calls 0x220; each level sets a register after its call returns
+        let code: &[(u16, &[u8])] = &[
+            (0x200, &[0x22, 0x10, 0x60, 0x01, 0x12, 0x04]),
+            (0x210, &[0x61, 0x02, 0x22, 0x20, 0x62, 0x03, 0x00, 0xEE]),
+            (0x220, &[0x63, 0x04, 0x00, 0xEE]),
+        ];
+        let mut chip8: Chip8 = Chip8::builder().strict(true).build().with_state(regs(&[]), 0, 0x200, code);
+        let mut trace: Vec<u16> = Vec::new();
+        for _ in 0 .. 9 {
+            trace.push(chip8.pc());
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(trace, vec![0x200, 0x210, 0x212, 0x220, 0x222, 0x214, 0x216, 0x202, 0x204]);
+        assert_eq!(&chip8.reg_v()[0 .. 4], &[1, 2, 3, 4]);
+        assert_eq!((chip8.pc(), chip8.sp()), (0x204, 0));
+
+        // Recursing to the full stack depth unwinds back to the first call, once per level
+        let mut chip8: Chip8 = Chip8::builder().strict(true).build();
+        chip8.load_rom_hex("600F 2206 1204 3000 120C 00EE 70FF 2206 7101 00EE").unwrap();
+        let mut deepest: u8 = 0;
+        for _ in 0 .. 200 {
+            chip8.cycle().unwrap();
+            deepest = deepest.max(chip8.sp());
+        }
+        assert_eq!(deepest, 16);
+        assert_eq!((chip8.pc(), chip8.reg_v()[1], chip8.sp()), (0x204, 15, 0));
+    }
+
+    #[test]
+    fn opcodes_1nnn_and_bnnn_jump() {
+        assert_eq!(run_opcode(Chip8::new(), regs(&[]), 0, 0x1345).pc(), 0x345);
+        assert_eq!(run_opcode(Chip8::new(), regs(&[(0, 0x10), (1, 0x20)]), 0, 0xB300).pc(), 0x310);
+    }
+
+    #[test]
+    fn skip_opcodes_compare_registers_and_immediates() {
+        // (V1, V2, opcode, whether it skips)
+        let cases: [(u8, u8, u16, bool); 8] = [
+            (0x42, 0x00, 0x3142, true),
+            (0x42, 0x00, 0x3143, false),
+            (0x42, 0x00, 0x4142, false),
+            (0x42, 0x00, 0x4143, true),
+            (0x42, 0x42, 0x5120, true),
+            (0x42, 0x43, 0x5120, false),
+            (0x42, 0x42, 0x9120, false),
+            (0x42, 0x43, 0x9120, true),
+        ];
+        for (v1, v2, opcode, skips) in cases {
+            let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(1, v1), (2, v2)]), 0, opcode);
+            assert_eq!(chip8.pc(), if skips { 0x204 } else { 0x202 }, "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn opcodes_6xnn_and_7xnn_load_and_add_without_flag() {
+        assert_eq!(run_opcode(Chip8::new(), regs(&[]), 0, 0x65AB).reg_v()[5], 0xAB);
+
+        // 7XNN wraps around and leaves VF alone
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(1, 0xFF), (0xF, 0x07)]), 0, 0x7102);
+        assert_eq!((chip8.reg_v()[1], chip8.reg_v()[0xF]), (0x01, 0x07));
+    }
+
+    #[test]
+    fn alu_opcodes_set_result_and_flag() {
+        // (VX, VY, opcode, expected VX, expected VF); VF starts at 7 to show when it's left alone
+        let cases: [(u8, u8, u16, u8, u8); 13] = [
+            (0x11, 0x22, 0x8120, 0x22, 0x07),
+            (0xF0, 0x0F, 0x8121, 0xFF, 0x00),
+            (0xF0, 0x3C, 0x8122, 0x30, 0x00),
+            (0xF0, 0x3C, 0x8123, 0xCC, 0x00),
+            (0xF0, 0x20, 0x8124, 0x10, 0x01),
+            (0x10, 0x20, 0x8124, 0x30, 0x00),
+            (0x30, 0x10, 0x8125, 0x20, 0x01),
+            (0x10, 0x30, 0x8125, 0xE0, 0x00),
+            (0xFF, 0x05, 0x8126, 0x02, 0x01),
+            (0x10, 0x30, 0x8127, 0x20, 0x01),
+            (0x30, 0x10, 0x8127, 0xE0, 0x00),
+            (0x00, 0x81, 0x812E, 0x02, 0x01),
+            (0x00, 0x41, 0x812E, 0x82, 0x00),
+        ];
+        for (vx, vy, opcode, expected_vx, expected_vf) in cases {
+            let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(1, vx), (2, vy), (0xF, 0x07)]), 0, opcode);
+            assert_eq!((chip8.reg_v()[1], chip8.reg_v()[0xF]), (expected_vx, expected_vf), "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn alu_opcodes_follow_vf_reset_quirk_and_write_flag_last() {
+        let quirks: Quirks = Quirks { vf_reset: false, ..Quirks::default() };
+        let chip8: Chip8 = run_opcode(Chip8::builder().quirks(quirks).build(), regs(&[(1, 0xF0), (2, 0x0F), (0xF, 0x07)]), 0, 0x8121);
+        assert_eq!((chip8.reg_v()[1], chip8.reg_v()[0xF]), (0xFF, 0x07));
+
+        // With VF as the destination, the flag overwrites the sum
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(1, 0x01), (0xF, 0xFF)]), 0, 0x8F14);
+        assert_eq!(chip8.reg_v()[0xF], 0x01);
+    }
+
+    #[test]
+    fn opcodes_annn_and_cxnn_set_i_and_random_values() {
+        assert_eq!(run_opcode(Chip8::new(), regs(&[]), 0, 0xA123).reg_i(), 0x123);
+
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.set_rng_sequence(vec![0xAB]);
+        assert_eq!(run_opcode(chip8, regs(&[]), 0, 0xC30F).reg_v()[3], 0x0B);
+    }
+
+    #[test]
+    fn key_skip_opcodes_read_the_keypad() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.keypad[0x5] = true;
+        let pressed: Chip8 = run_opcode(chip8, regs(&[(1, 0x5)]), 0, 0xE19E);
+        assert_eq!(pressed.pc(), 0x204);
+        assert_eq!(run_opcode(Chip8::new(), regs(&[(1, 0x5)]), 0, 0xE19E).pc(), 0x202);
+        assert_eq!(run_opcode(Chip8::new(), regs(&[(1, 0x5)]), 0, 0xE1A1).pc(), 0x204);
+    }
+
+    #[test]
+    fn timer_opcodes_set_and_read_timers() {
+        let mut chip8: Chip8 = Chip8::new().with_state(regs(&[(1, 9), (3, 4)]), 0, 0x200, &[(0x200, &[0xF1, 0x15, 0xF2, 0x07, 0xF3, 0x18])]);
+        for _ in 0 .. 3 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!((chip8.delay_timer(), chip8.reg_v()[2], chip8.sound_timer()), (9, 9, 4));
+    }
+
+    #[test]
+    fn opcode_fx0a_waits_for_a_key() {
+        let mut chip8: Chip8 = run_opcode(Chip8::new(), regs(&[]), 0, 0xF40A);
+        assert_eq!(chip8.pc(), 0x200);
+
+        chip8.keypad[0x3] = true;
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.pc(), chip8.reg_v()[4]), (0x202, 0x3));
+    }
+
+    #[test]
+    fn opcode_fx29_points_at_font_digit() {
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(5, 0xA)]), 0, 0xF529);
+        assert_eq!(chip8.reg_i(), FONT_START_ADDRESS + 50);
+
+        // Only the low nibble counts, unless strict
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(5, 0x1A)]), 0, 0xF529);
+        assert_eq!(chip8.reg_i(), FONT_START_ADDRESS + 50);
+        let mut strict: Chip8 = Chip8::builder().strict(true).build()
+            .with_state(regs(&[(5, 0x1A)]), 0, 0x200, &[(0x200, &[0xF5, 0x29])]);
+        assert_eq!(strict.cycle(), Err(Chip8Error::InvalidFontDigit(0x1A)));
+    }
+
+    #[test]
+    fn opcode_fx33_stores_decimal_digits() {
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(0, 254)]), 0x300, 0xF033);
+        assert_eq!([chip8.peek(0x300), chip8.peek(0x301), chip8.peek(0x302)], [Ok(2), Ok(5), Ok(4)]);
+        assert_eq!(chip8.reg_i(), 0x300);
+    }
+
+    #[test]
+    fn opcodes_fx55_and_fx65_copy_registers() {
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(0, 1), (1, 2), (2, 3), (3, 4)]), 0x300, 0xF255);
+        let stored: Vec<u8> = (0x300 .. 0x304).map(|addr| chip8.peek(addr).unwrap()).collect();
+        assert_eq!(stored, vec![1, 2, 3, 0]);
+        assert_eq!(chip8.reg_i(), 0x303);
+
+        let quirks: Quirks = Quirks { load_increment: false, ..Quirks::default() };
+        let chip8: Chip8 = Chip8::builder().quirks(quirks).build()
+            .with_state(regs(&[]), 0, 0x200, &[(0x300, &[9, 8, 7])]);
+        let chip8: Chip8 = run_opcode(chip8, regs(&[(2, 0xEE)]), 0x300, 0xF165);
+        assert_eq!(&chip8.reg_v()[.. 3], &[9, 8, 0xEE]);
+        assert_eq!(chip8.reg_i(), 0x300);
+    }
+
+    #[test]
+    fn opcodes_fx55_and_fx65_include_vx() {
+        // F265 loads V0, V1 and V2 (not just up to V1) and moves I past all three
+        let chip8: Chip8 = Chip8::new().with_state(regs(&[]), 0, 0x200, &[(0x300, &[9, 8, 7, 6])]);
+        let chip8: Chip8 = run_opcode(chip8, regs(&[(3, 0xEE)]), 0x300, 0xF265);
+        assert_eq!(&chip8.reg_v()[.. 4], &[9, 8, 7, 0xEE]);
+        assert_eq!(chip8.reg_i(), 0x303);
+
+        // X = 0 still copies one register, and X = F copies all 16
+        let chip8: Chip8 = run_opcode(Chip8::new(), regs(&[(0, 5), (1, 6)]), 0x300, 0xF055);
+        assert_eq!([chip8.peek(0x300), chip8.peek(0x301)], [Ok(5), Ok(0)]);
+        assert_eq!(chip8.reg_i(), 0x301);
+        let reg_v: [u8; 16] = std::array::from_fn(|reg| reg as u8 + 1);
+        let chip8: Chip8 = run_opcode(Chip8::new(), reg_v, 0x300, 0xFF55);
+        let stored: Vec<u8> = (0x300 .. 0x311).map(|addr| chip8.peek(addr).unwrap()).collect();
+        assert_eq!(stored, [&reg_v[..], &[0]].concat());
+        assert_eq!(chip8.reg_i(), 0x310);
+
+        // Without the increment quirk, I stays put but the range is the same
+        let quirks: Quirks = Quirks { store_increment: false, ..Quirks::default() };
+        let chip8: Chip8 = run_opcode(Chip8::builder().quirks(quirks).build(), regs(&[(0, 1), (1, 2), (2, 3)]), 0x300, 0xF255);
+        assert_eq!([chip8.peek(0x302), chip8.peek(0x303)], [Ok(3), Ok(0)]);
+        assert_eq!(chip8.reg_i(), 0x300);
+    }
+
+    #[test]
+    fn await_key_mode_picks_between_held_keys() {
+        // Press 9, then 2, while FX0A waits
+        for (mode, expected) in [(AwaitKeyMode::LowestIndex, 0x2), (AwaitKeyMode::FirstPress, 0x9)] {
+            let mut chip8: Chip8 = run_opcode(Chip8::builder().await_key_mode(mode).build(), regs(&[]), 0, 0xF40A);
+            chip8.set_key(0x9, true);
+            chip8.set_key(0x2, true);
+            chip8.cycle().unwrap();
+            assert_eq!(chip8.reg_v()[4], expected, "{:?}", mode);
+        }
+
+        // Releasing and pressing again moves a key to the back
+        let mut chip8: Chip8 = Chip8::builder().await_key_mode(AwaitKeyMode::FirstPress).build();
+        chip8.set_key(0x9, true);
+        chip8.set_key(0x2, true);
+        chip8.set_key(0x9, false);
+        chip8.set_key(0x9, true);
+        let chip8: Chip8 = run_opcode(chip8, regs(&[]), 0, 0xF40A);
+        assert_eq!(chip8.reg_v()[4], 0x2);
+    }
+
+    #[test]
+    #[should_panic(expected = "has no pixels")]
+    fn set_resolution_rejects_an_empty_screen() {
+        Chip8::new().set_resolution(128, 0);
+    }
+
+    #[test]
+    fn memory_size_is_configurable() {
+        assert_eq!(Chip8::new().memory_size(), 4096);
+        assert_eq!(Chip8::builder().memory_size(100).build().memory_size(), 4096);
+
+        // With 64K, addresses past 0x0FFF are real memory, and only 0xFFFF wraps
+        let chip8: Chip8 = Chip8::builder().memory_size(MAX_MEMORY_SIZE).build();
+        assert_eq!(chip8.peek(FONT_START_ADDRESS), Ok(0xF0));
+        let chip8: Chip8 = run_opcode(chip8, regs(&[(0, 123)]), 0xFFFE, 0xF033);
+        assert_eq!([chip8.peek(0xFFFE), chip8.peek(0xFFFF), chip8.peek(0x0000)], [Ok(1), Ok(2), Ok(3)]);
+        assert_eq!(chip8.peek(0x0FFE), Ok(0));
+    }
+
+    #[test]
+    fn drawing_follows_the_active_resolution() {
+        let code: &[(u16, &[u8])] = &[(0x200, &[0xD0, 0x11, 0x00, 0xE0]), (0x300, &[0x80])];
+        let mut chip8: Chip8 = Chip8::new().with_state(regs(&[(0, 100), (1, 40)]), 0x300, 0x200, code);
+        chip8.set_resolution(128, 64);
+        assert_eq!((chip8.width(), chip8.height(), chip8.graphics_buffer.len()), (128, 64, 128 * 64));
+
+        // Coordinates past 64x32 land on the screen rather than wrapping
+        chip8.cycle().unwrap();
+        assert!(chip8.graphics_buffer[40 * 128 + 100]);
+        assert_eq!(chip8.render_ascii().lines().count(), 64);
+
+        chip8.cycle().unwrap();
+        assert!(chip8.graphics_buffer.iter().all(|lit| !lit));
+    }
+
+    #[test]
+    fn rpl_flags_round_trip_through_fx75_and_fx85() {
+        let saved: Rc<RefCell<Vec<[u8; RPL_FLAG_COUNT]>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink: Rc<RefCell<Vec<[u8; RPL_FLAG_COUNT]>>> = Rc::clone(&saved);
+        let code: &[(u16, &[u8])] = &[(0x200, &[0xF2, 0x75, 0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF1, 0x85, 0xF8, 0x75])];
+        let mut chip8: Chip8 = Chip8::builder()
+            .extended_opcodes(true)
+            .rpl_flags([0, 0, 0, 9, 9, 9, 9, 9])
+            .build()
+            .with_state(regs(&[(0, 1), (1, 2), (2, 3)]), 0, 0x200, code);
+        chip8.set_rpl_callback(move |flags| sink.borrow_mut().push(*flags));
+
+        // FX75 stores V0..VX and leaves the other flags alone
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.rpl_flags(), [1, 2, 3, 9, 9, 9, 9, 9]);
+        assert_eq!(*saved.borrow(), [[1, 2, 3, 9, 9, 9, 9, 9]]);
+
+        // FX85 loads only V0..VX back
+        for _ in 0 .. 4 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.reg_v()[.. 3], [1, 2, 0]);
+
+        // There are only 8 flags, so F875 isn't an RPL opcode
+        chip8.cycle().unwrap();
+        assert_eq!(saved.borrow().len(), 1);
+    }
+
+    #[test]
+    fn opcodes_ex9e_and_exa1_use_the_low_nibble_of_large_keys() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.set_key(0x2, true);
+        let chip8: Chip8 = run_opcode(chip8, regs(&[(0, 0x12)]), 0, 0xE09E);
+        assert_eq!(chip8.pc(), 0x204);
+
+        let mut strict: Chip8 = Chip8::builder().strict(true).build()
+            .with_state(regs(&[(0, 0x12)]), 0, 0x200, &[(0x200, &[0xE0, 0xA1])]);
+        assert_eq!(strict.cycle(), Err(Chip8Error::InvalidKey(0x12)));
+    }
+
+    #[test]
+    fn random_roms_never_panic() {
+        // A quick version of the fuzz example: errors are fine, panics aren't
+        let mut rng: fastrand::Rng = fastrand::Rng::with_seed(0xC8);
+        for _ in 0 .. 300 {
+            let rom: Vec<u8> = (0 .. rng.usize(1 ..= 256)).map(|_| rng.u8(..)).collect();
+            let mut chip8: Chip8 = Chip8::builder()
+                .strict(rng.bool())
+                .extended_opcodes(rng.bool())
+                .stack_depth(rng.u8(1 ..= 16))
+                .memory_size(if rng.bool() { MEMORY_SIZE } else { MAX_MEMORY_SIZE })
+                .build();
+            chip8.load_rom_bytes(&rom).unwrap();
+            for cycle in 0 .. 2000 {
+                if cycle % 10 == 0 {
+                    chip8.tick_timers();
+                    chip8.set_key(rng.u8(.. 16), rng.bool());
+                }
+                if chip8.cycle().is_err() || chip8.status().is_stopped() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pc_wraps_past_the_end_of_64k_memory() {
+        let big = || Chip8::builder().memory_size(MAX_MEMORY_SIZE).build();
+
+        // Running off the end of memory goes back to 0x0000, in both modes
+        for strict in [false, true] {
+            let code: &[(u16, &[u8])] = &[(0xFFFC, &[0x60, 0x01, 0x61, 0x02])];
+            let mut chip8: Chip8 = Chip8::builder().memory_size(MAX_MEMORY_SIZE).strict(strict).build()
+                .with_state(regs(&[]), 0, 0xFFFC, code);
+            chip8.cycle().unwrap();
+            chip8.cycle().unwrap();
+            assert_eq!((chip8.pc(), chip8.reg_v()[0], chip8.reg_v()[1]), (0x0000, 1, 2), "strict={}", strict);
+        }
+
+        // Skips from the last two instructions wrap too
+        let code: &[(u16, &[u8])] = &[(0xFFFC, &[0x30, 0x00, 0x30, 0x00])];
+        let mut chip8: Chip8 = big().with_state(regs(&[]), 0, 0xFFFC, code);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.pc(), 0x0000);
+        let mut chip8: Chip8 = big().with_state(regs(&[]), 0, 0xFFFE, code);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.pc(), 0x0002);
+
+        // So does waiting for a key at the very end, which steps the PC back
+        let code: &[(u16, &[u8])] = &[(0xFFFE, &[0xF0, 0x0A])];
+        let mut chip8: Chip8 = big().with_state(regs(&[]), 0, 0xFFFE, code);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.pc(), 0xFFFE);
+    }
+
+    #[test]
+    fn set_keypad_keeps_the_press_order() {
+        let mut chip8: Chip8 = run_opcode(Chip8::builder().await_key_mode(AwaitKeyMode::FirstPress).build(), regs(&[]), 0, 0xF00A);
+        chip8.set_key(0x9, true);
+        let mut state: [bool; 16] = [false; 16];
+        state[0x9] = true;
+        state[0x2] = true;
+        chip8.set_keypad(state);
+        assert_eq!(chip8.keypad_state(), state);
+
+        // 9 was held first, so setting the same keypad again must not move 2 ahead of it
+        chip8.set_keypad(state);
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.reg_v()[0], 0x9);
+    }
+
+    #[test]
+    fn skip_delay_loops_fast_forwards_the_wait() {
+        // V3 = 30; DT = V3; ST = V3; loop: V3 = DT; skip if V3 == 0; jump loop; V4 = 1
+        let code: &[(u16, &[u8])] = &[(0x200, &[0x63, 0x1E, 0xF3, 0x15, 0xF3, 0x18, 0xF3, 0x07, 0x33, 0x00, 0x12, 0x06, 0x64, 0x01])];
+        let mut plain: Chip8 = Chip8::new().with_state(regs(&[]), 0, 0x200, code);
+        let mut skipping: Chip8 = Chip8::builder().skip_delay_loops(true).build().with_state(regs(&[]), 0, 0x200, code);
+        for chip8 in [&mut plain, &mut skipping] {
+            for _ in 0 .. 3 {
+                chip8.cycle().unwrap();
+            }
+        }
+
+        // Without skipping, the loop keeps running until the timer is ticked down
+        for _ in 0 .. 30 {
+            assert_eq!(plain.reg_v()[4], 0);
+            plain.run_frame(10).unwrap();
+        }
+        plain.run_frame(10).unwrap();
+        assert_eq!(plain.reg_v()[4], 1);
+
+        // With it, the loop finishes in one step, with the sound timer run down alongside
+        skipping.cycle().unwrap();
+        assert_eq!(skipping.pc(), 0x20C);
+        assert_eq!((skipping.delay_timer(), skipping.sound_timer(), skipping.reg_v()[3]), (0, 0, 0));
+        skipping.cycle().unwrap();
+        assert_eq!(skipping.reg_v()[4], 1);
+
+        // Jumping anywhere but back to the FX07 isn't the wait loop
+        let code: &[(u16, &[u8])] = &[(0x200, &[0xF3, 0x07, 0x33, 0x00, 0x12, 0x02])];
+        let mut other: Chip8 = Chip8::builder().skip_delay_loops(true).build().with_state(regs(&[]), 0, 0x200, code);
+        other.reg_delay = 5;
+        other.cycle().unwrap();
+        assert_eq!((other.pc(), other.delay_timer()), (0x202, 5));
+    }
+
+    #[test]
+    fn skip_delay_loops_ignores_lookalikes_past_4k() {
+        // V3 = DT; skip if V3 == 0; jump to 0x206, which isn't this FX07 at 0x1206
+        let code: &[(u16, &[u8])] = &[(0x1206, &[0xF3, 0x07, 0x33, 0x00, 0x12, 0x06])];
+        let mut chip8: Chip8 = Chip8::builder()
+            .memory_size(MAX_MEMORY_SIZE)
+            .skip_delay_loops(true)
+            .build()
+            .with_state(regs(&[]), 0, 0x1206, code);
+        chip8.reg_delay = 5;
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.pc(), chip8.delay_timer(), chip8.reg_v()[3]), (0x1208, 5, 5));
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.pc(), 0x206);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn analysis_json_combines_regions_and_disassembly() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0xA2, 0x2A, 0xFF, 0xFF, 0x12]).unwrap();
+        let analysis: serde_json::Value = serde_json::from_str(&chip8.analysis_json()).unwrap();
+
+        assert_eq!(analysis["memory_size"], 4096);
+        assert_eq!(analysis["entry_point"], 0x200);
+        assert_eq!(analysis["regions"][2], serde_json::json!({ "name": "rom", "start": 0x200, "end": 0x205 }));
+        assert_eq!(analysis["regions"][3]["start"], 0x205);
+        assert_eq!(analysis["disassembly"], serde_json::json!([
+            { "address": 0x200, "opcode": 0xA22A, "text": "LD I, #22A" },
+            { "address": 0x202, "opcode": 0xFFFF, "text": null },
+            { "address": 0x204, "opcode": 0x12, "text": null },
+        ]));
+    }
+
+    #[test]
+    fn step_traced_reports_changes() {
+        // V1 += 2; [I..I+2] = BCD of V1
+        let code: &[(u16, &[u8])] = &[(0x200, &[0x71, 0x02, 0xF1, 0x33, 0x00, 0xE0])];
+        let mut chip8: Chip8 = Chip8::builder().undo_depth(4).build().with_state(regs(&[(1, 4)]), 0x300, 0x200, code);
+
+        let step: StepResult = chip8.step_traced().unwrap().unwrap();
+        assert_eq!((step.pc_before, step.opcode, step.mnemonic.as_deref()), (0x200, 0x7102, Some("ADD V1, #02")));
+        assert_eq!(step.changed_registers, [(1, 4, 6)]);
+        assert!(step.changed_memory.is_empty());
+
+        // Only the digits that changed are listed, and traced steps can still be undone
+        let step: StepResult = chip8.step_traced().unwrap().unwrap();
+        assert_eq!(step.changed_memory, [(0x302, 0, 6)]);
+        assert!(chip8.undo_step().is_some());
+        assert_eq!(chip8.peek(0x302), Ok(0));
+    }
+
+    #[test]
+    fn undo_restores_rpl_flags_saved_by_fx75() {
+        // Save V0..V1 to the flags, twice
+        let code: &[(u16, &[u8])] = &[(0x200, &[0xF1, 0x75, 0xF1, 0x75])];
+        let saved: Rc<RefCell<Vec<[u8; RPL_FLAG_COUNT]>>> = Rc::new(RefCell::new(Vec::new()));
+        let mut chip8: Chip8 = Chip8::builder().extended_opcodes(true).undo_depth(4).build()
+            .with_state(regs(&[(0, 5), (1, 6)]), 0, 0x200, code);
+        let saved_clone: Rc<RefCell<Vec<[u8; RPL_FLAG_COUNT]>>> = Rc::clone(&saved);
+        chip8.set_rpl_callback(move |flags| saved_clone.borrow_mut().push(*flags));
+
+        let step: StepResult = chip8.step_traced().unwrap().unwrap();
+        assert_eq!(step.changed_flags, [(0, 0, 5), (1, 0, 6)]);
+        assert_eq!(chip8.rpl_flags()[.. 2], [5, 6]);
+
+        // Saving the same values again changes nothing
+        assert!(chip8.step_traced().unwrap().unwrap().changed_flags.is_empty());
+        chip8.undo_step().unwrap();
+        assert_eq!(chip8.rpl_flags()[.. 2], [5, 6]);
+
+        // Undoing the first save puts the old flags back and tells the callback
+        chip8.undo_step().unwrap();
+        assert_eq!(chip8.rpl_flags(), [0; RPL_FLAG_COUNT]);
+        assert_eq!(saved.borrow().last(), Some(&[0; RPL_FLAG_COUNT]));
+    }
+
+    #[test]
+    fn display_dirty_covers_the_whole_frame() {
+        // Draw the "0" glyph twice in the same place, then loop
+        let rom: &str = "A050 D005 D005 1206";
+
+        // Both draws land in one frame, leaving the screen blank at its end
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex(rom).unwrap();
+        chip8.take_display_dirty();
+        chip8.run_frame(10).unwrap();
+        assert!(chip8.take_display_dirty());
+        assert!(chip8.graphics_buffer.iter().all(|pixel| !pixel));
+
+        // With one draw per frame, each frame shows one draw and the held back one doesn't count early
+        let quirks: Quirks = Quirks { draws_per_frame: Some(1), ..Quirks::default() };
+        let mut chip8: Chip8 = Chip8::builder().quirks(quirks).build();
+        chip8.load_rom_hex(rom).unwrap();
+        chip8.take_display_dirty();
+        chip8.run_frame(10).unwrap();
+        assert!(chip8.take_display_dirty());
+        assert!(chip8.graphics_buffer.iter().any(|pixel| *pixel));
+        chip8.run_frame(10).unwrap();
+        assert!(chip8.take_display_dirty());
+        assert!(chip8.graphics_buffer.iter().all(|pixel| !pixel));
+        chip8.run_frame(10).unwrap();
+        assert!(!chip8.take_display_dirty());
+    }
+
+    #[test]
+    fn hex_roms_mix_words_and_bytes_and_skip_comments() {
+        let mut chip8: Chip8 = Chip8::new();
+        let rom: &str = "; Clear the screen\n00E0 12 34 ; a word, then two bytes\n\n0x6A 0XBCDE;no space before this\n";
+        chip8.load_rom_hex(rom).unwrap();
+        assert_eq!(chip8.rom_size, 7);
+        let start: usize = PC_START_ADDRESS as usize;
+        assert_eq!(chip8.memory[start .. start + 8], [0x00, 0xE0, 0x12, 0x34, 0x6A, 0xBC, 0xDE, 0x00]);
+    }
+
+    #[test]
+    fn hex_rom_errors_name_the_line_and_token() {
+        let mut chip8: Chip8 = Chip8::new();
+        let error: Error = chip8.load_rom_hex("6001 ; fine\n6002 12G4\n").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().contains("'12G4'"));
+        assert!(error.to_string().contains("line 2"));
+
+        // Tokens must be a whole byte or word, and nothing is loaded when one isn't
+        let error: Error = chip8.load_rom_hex("A05\n").unwrap_err();
+        assert!(error.to_string().contains("'A05'"));
+        assert!(error.to_string().contains("line 1"));
+        assert_eq!(chip8.rom_size, 0);
+    }
+
+    #[test]
+    fn step_until_counts_cycles_and_stops_at_the_limit() {
+        // Increment V1 in a loop, taking two cycles per pass
+        let rom: &str = "7101 1200";
+        let reached = |chip8: &Chip8| chip8.reg_v[1] == 5;
+
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex(rom).unwrap();
+        assert_eq!(chip8.step_until(reached, 100), Ok(9));
+        // A predicate that already holds takes no cycles
+        assert_eq!(chip8.step_until(reached, 100), Ok(0));
+
+        // Holding right at the limit still counts, one cycle short doesn't
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex(rom).unwrap();
+        assert_eq!(chip8.step_until(reached, 9), Ok(9));
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.load_rom_hex(rom).unwrap();
+        assert_eq!(chip8.step_until(reached, 8), Err(Chip8Error::CycleLimitReached(8)));
+        assert_eq!(chip8.reg_v[1], 4);
+    }
+    #[test]
+    fn keys_past_the_keypad_are_ignored() {
+        let mut chip8: Chip8 = Chip8::new();
+        chip8.set_key(0x10, true);
+        chip8.set_key(0xFF, true);
+        assert_eq!(chip8.keypad_state(), [false; 16]);
+        assert!(chip8.press_order.is_empty());
 
-        self.reg_i += self.instr.x() as u16 + 1;
+        chip8.set_key(0xF, true);
+        assert!(chip8.keypad_state()[0xF]);
     }
 }
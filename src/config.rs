@@ -0,0 +1,201 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: config.rs                          //
+// Description: Per-ROM configuration       //
+//              and RPL flag files.         //
+// ---------------------------------------- //
+
+use crate::options::{Options, PERSISTED_OPTIONS, STDIN_ROM_PATH};
+use chip8_rust::chip8::RPL_FLAG_COUNT;
+
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+/// Gets the path of a per-ROM file with the given extension, keyed by the ROM's file name. ROMs from stdin
+/// have none.
+fn config_path(rom_path: &str, extension: &str) -> Option<PathBuf> {
+    if rom_path == STDIN_ROM_PATH {
+        return None;
+    }
+    let rom_name = Path::new(rom_path).file_name()?;
+    let mut path: PathBuf = dirs::config_dir()?.join("chip8-rust");
+    path.push(rom_name);
+    path.set_extension(extension);
+    Some(path)
+}
+
+/// Loads the saved command line arguments for a ROM, if there are any.
+pub fn load_args(rom_path: &str) -> Vec<String> {
+    match config_path(rom_path, "cfg") {
+        Some(path) => read_args(&path),
+        None => Vec::new(),
+    }
+}
+
+/// Reads command line arguments saved one per line, giving none if the file can't be read.
+fn read_args(path: &Path) -> Vec<String> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    text.lines().map(str::to_string).collect()
+}
+
+/// Attempts to layer the saved settings for a ROM under the command line `args`, which were parsed into
+/// `options`. See `merge_saved_args`.
+pub fn apply_saved_args(options: &Options, args: &[String]) -> Option<Options> {
+    merge_saved_args(load_args(&options.rom_path), args)
+}
+
+/// Attempts to parse the `saved` arguments followed by the command line `args`, so the command line takes
+/// precedence. Saved options that are no longer remembered are dropped, as are saved quirks when the command line
+/// names a quirks file, and ones the command line can't be combined with are skipped with a warning. Returns None
+/// if no saved option applied.
+fn merge_saved_args(saved_args: Vec<String>, args: &[String]) -> Option<Options> {
+    // Group the saved arguments into options with their values
+    let mut saved: Vec<Vec<String>> = Vec::new();
+    for arg in saved_args {
+        match saved.last_mut() {
+            Some(option) if !arg.starts_with("--") => option.push(arg),
+            _ => saved.push(vec![arg]),
+        }
+    }
+
+    // Saved quirks are relative to the saved quirks file, so a new file replaces them all
+    let new_quirks_file: bool = args.iter().any(|arg| arg == "--quirks");
+    let is_remembered = |option: &Vec<String>| {
+        PERSISTED_OPTIONS.contains(&option[0].as_str()) && !(new_quirks_file && option[0] != "--speed")
+    };
+
+    let mut kept: Vec<String> = Vec::new();
+    let mut combined: Option<Options> = None;
+    for option in saved.into_iter().filter(is_remembered) {
+        let trial: Vec<String> = kept.iter().chain(option.iter()).chain(args.iter()).cloned().collect();
+        match Options::parse(trial.into_iter()) {
+            Ok(parsed) => {
+                kept.extend(option);
+                combined = Some(parsed);
+            },
+            Err(_) => eprintln!("Warning: Ignoring saved option '{}', which doesn't work with this command line.", option.join(" ")),
+        }
+    }
+    combined
+}
+
+/// Saves command line arguments for a ROM, one argument per line.
+pub fn save_args(rom_path: &str, args: &[String]) -> Result<(), Error> {
+    match config_path(rom_path, "cfg") {
+        Some(path) => write_args(&path, args),
+        None => Ok(()),
+    }
+}
+
+/// Writes command line arguments one per line, creating the directory if needed.
+fn write_args(path: &Path, args: &[String]) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, args.join("\n") + "\n")
+}
+
+/// Loads the saved RPL flags for a ROM, if there are any.
+pub fn load_flags(rom_path: &str) -> Option<[u8; RPL_FLAG_COUNT]> {
+    let bytes: Vec<u8> = fs::read(config_path(rom_path, "rpl")?).ok()?;
+    bytes.try_into().ok()
+}
+
+/// Saves the RPL flags for a ROM as raw bytes.
+pub fn save_flags(rom_path: &str, flags: &[u8; RPL_FLAG_COUNT]) -> Result<(), Error> {
+    let Some(path) = config_path(rom_path, "rpl") else {
+        return Ok(());
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8_rust::quirks::Quirks;
+
+    use std::env;
+    use std::process;
+
+    /// Splits a command line into arguments.
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn saved_args_round_trip_through_a_file() {
+        let path: PathBuf = env::temp_dir().join(format!("chip8-rust-test-{}", process::id())).join("rom.cfg");
+        let options: Options = Options::parse(args("rom.ch8 --speed 900").into_iter()).unwrap();
+        // Quirks changed while running are saved along with the command line's settings
+        let quirks: Quirks = Quirks { shift: true, draws_per_frame: Some(2), ..Quirks::default() };
+        let mut saved: Vec<String> = options.to_args(&quirks);
+        saved.push("a value with spaces".to_string());
+
+        write_args(&path, &saved).unwrap();
+        let mut loaded: Vec<String> = read_args(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(read_args(&path), Vec::<String>::new());
+
+        loaded.pop();
+        let merged: Options = merge_saved_args(loaded, &args("rom.ch8")).unwrap();
+        assert_eq!(merged.speed, 900);
+        assert_eq!(merged.quirks, quirks);
+    }
+
+    #[test]
+    fn command_line_overrides_saved_args() {
+        let saved: Vec<String> = args("--speed 900 --quirk shift=true --quirk draws_per_frame=2 --debug-audio");
+
+        // The command line wins where both set something, and saved options that aren't remembered are dropped
+        let merged: Options = merge_saved_args(saved.clone(), &args("rom.ch8 --speed 700 --quirk shift=false --headless"))
+            .unwrap();
+        assert_eq!(merged.speed, 700);
+        assert_eq!(merged.quirks, Quirks { draws_per_frame: Some(2), ..Quirks::default() });
+        assert!(!merged.debug_audio);
+
+        // Saved options that don't parse are skipped, keeping the rest
+        let merged: Options = merge_saved_args(args("--speed fast --quirk shift=true"), &args("rom.ch8")).unwrap();
+        assert_eq!(merged.speed, Options::parse(args("rom.ch8").into_iter()).unwrap().speed);
+        assert!(merged.quirks.shift);
+
+        assert!(merge_saved_args(Vec::new(), &args("rom.ch8")).is_none());
+        assert!(merge_saved_args(args("--debug-audio"), &args("rom.ch8")).is_none());
+    }
+    #[test]
+    #[cfg(feature = "quirks-file")]
+    fn quirks_files_are_saved_by_full_path_and_replace_saved_quirks() {
+        let dir: PathBuf = env::temp_dir().join(format!("chip8-rust-quirks-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let quirks_path: PathBuf = dir.join(".").join("quirks.toml");
+        fs::write(&quirks_path, "wrap_x = true\n").unwrap();
+        let quirks_arg: String = quirks_path.to_str().unwrap().to_string();
+
+        // Only quirks that differ from the file are saved, with the file's full path
+        let options: Options = Options::parse(["rom.ch8".to_string(), "--quirks".to_string(), quirks_arg.clone()].into_iter())
+            .unwrap();
+        let quirks: Quirks = Quirks { wrap_x: true, wrap_y: true, ..Quirks::default() };
+        let saved: Vec<String> = options.to_args(&quirks);
+        let full_path: String = fs::canonicalize(&quirks_path).unwrap().to_str().unwrap().to_string();
+        assert_eq!(&saved[2 ..], &["--quirks".to_string(), full_path, "--quirk".to_string(), "wrap_y=true".to_string()]);
+        assert_eq!(merge_saved_args(saved.clone(), &args("rom.ch8")).unwrap().quirks, quirks);
+
+        // A quirks file on the command line replaces the saved file and quirks
+        fs::write(dir.join("other.toml"), "shift = true\n").unwrap();
+        let other_arg: String = dir.join("other.toml").to_str().unwrap().to_string();
+        let merged: Options = merge_saved_args(saved, &["rom.ch8".to_string(), "--quirks".to_string(), other_arg]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(merged.quirks, Quirks { shift: true, ..Quirks::default() });
+    }
+}
@@ -0,0 +1,298 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: debugger.rs                        //
+// Description: Interactive debugger        //
+//              console.                    //
+// ---------------------------------------- //
+
+use chip8_rust::chip8::{Chip8, StepResult};
+use chip8_rust::error::Chip8Error;
+
+use std::collections::BTreeSet;
+use std::io::{self, Error, Write};
+
+/// Help text listing the debugger commands.
+const HELP: &str = "Commands:
+  step [N]            Run N instructions (default 1)      (s)
+  next                Step over calls                     (n)
+  undo [N]            Reverse the last N instructions     (u)
+  continue            Run until a breakpoint              (c)
+  break [ADDR]        Set a breakpoint, or list them      (b)
+  delete ADDR         Remove a breakpoint                 (d)
+  regs                Show registers, timers and stack    (r)
+  mem ADDR [LEN]      Show LEN bytes of memory (default 16)
+  disas [ADDR] [N]    Disassemble N instructions (default PC, 10)
+  poke ADDR VALUE     Write a byte of memory
+  sprite [N]          Preview the N-row sprite at I (default 15)
+  grab X Y W H        Print a screen region as sprite bytes
+  quit                Quit the emulator                   (q)
+Numbers are decimal, or hex with a 0x prefix.";
+/// Number of instructions the debugger can undo.
+pub const UNDO_DEPTH: usize = 4096;
+/// Default number of bytes shown by `mem`.
+const DEFAULT_MEM_LENGTH: u16 = 16;
+/// Default number of instructions shown by `disas`.
+const DEFAULT_DISAS_COUNT: u16 = 10;
+/// Default number of rows shown by `sprite`, the tallest DXYN can draw.
+const DEFAULT_SPRITE_HEIGHT: u16 = 15;
+
+/// Represents a gdb-like console that controls execution from stdin.
+pub struct Debugger {
+    /// Addresses that pause execution when the PC reaches them.
+    breakpoints: BTreeSet<u16>,
+    /// Whether execution is waiting on the console.
+    paused: bool,
+    /// Where `next` pauses again: the address after a call, and the stack depth once it has returned.
+    return_point: Option<(u16, u8)>,
+}
+
+impl Debugger {
+    /// Initializes a debugger, paused before the first instruction.
+    pub fn new() -> Debugger {
+        println!("Debugger:\tPaused; type 'help' for commands");
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            paused: true,
+            return_point: None,
+        }
+    }
+
+    /// Checks whether execution is waiting on the console.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses if the PC has reached a breakpoint or a call run by `next` has returned, returning whether it did.
+    pub fn check_breakpoint(&mut self, chip8: &Chip8) -> bool {
+        if self.breakpoints.contains(&chip8.pc()) {
+            println!("Breakpoint at 0x{:04X}", chip8.pc());
+            self.pause();
+        }
+        else if self.return_point.is_some_and(|(addr, depth)| chip8.pc() == addr && chip8.sp() <= depth) {
+            print_next(chip8);
+            self.pause();
+        }
+        self.paused
+    }
+
+    /// Pauses after an execution error instead of quitting.
+    pub fn pause_on_error(&mut self, error: &Chip8Error) {
+        println!("Stopped: {}", error);
+        self.pause();
+    }
+
+    /// Runs the instruction at the PC. A 2NNN call runs until it returns, with the subroutine running at full
+    /// speed like `continue`; anything else is a single step.
+    pub fn step_over(&mut self, chip8: &mut Chip8) {
+        let opcode: u16 = match (chip8.peek(chip8.pc()), chip8.peek(chip8.pc().wrapping_add(1))) {
+            (Ok(high), Ok(low)) => u16::from_be_bytes([high, low]),
+            _ => 0,
+        };
+        if opcode & 0xF000 == 0x2000 {
+            self.return_point = Some((chip8.pc().wrapping_add(2), chip8.sp()));
+            self.paused = false;
+        }
+        else {
+            step(chip8, 1);
+        }
+    }
+
+    /// Waits on the console again, forgetting any unfinished `next`.
+    fn pause(&mut self) {
+        self.paused = true;
+        self.return_point = None;
+    }
+
+    /// Reads and runs one command. Returns false when the emulator should quit.
+    pub fn prompt(&mut self, chip8: &mut Chip8) -> Result<bool, Error> {
+        print!("(chip8) ");
+        io::stdout().flush()?;
+        let mut line: String = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let args: Vec<Option<u16>> = words.iter().skip(1).map(|word| parse_number(word)).collect();
+        match (words.first().copied(), args.as_slice()) {
+            (None, _) => (),
+            (Some("help" | "h"), _) => println!("{}", HELP),
+            (Some("step" | "s"), [] | [Some(_)]) => step(chip8, args.first().copied().flatten().unwrap_or(1)),
+            (Some("next" | "n"), []) => self.step_over(chip8),
+            (Some("undo" | "u"), [] | [Some(_)]) => {
+                let count: u16 = args.first().copied().flatten().unwrap_or(1);
+                for _ in 0 .. count {
+                    match chip8.undo_step() {
+                        Some(delta) => {
+                            if !delta.is_repeatable() {
+                                println!("Undid a random number at 0x{:04X}; running it again rolls a new one.", delta.pc());
+                            }
+                        },
+                        None => {
+                            println!("Nothing left to undo.");
+                            break;
+                        },
+                    }
+                }
+                print_next(chip8);
+            },
+            (Some("continue" | "c"), []) => self.paused = false,
+            (Some("break" | "b"), []) => {
+                let list: Vec<String> = self.breakpoints.iter().map(|addr| format!("0x{:04X}", addr)).collect();
+                println!("Breakpoints: {}", if list.is_empty() { "none".to_string() } else { list.join(" ") });
+            },
+            (Some("break" | "b"), [Some(addr)]) => {
+                self.breakpoints.insert(*addr);
+            },
+            (Some("delete" | "d"), [Some(addr)]) => {
+                if !self.breakpoints.remove(addr) {
+                    println!("No breakpoint at 0x{:04X}", addr);
+                }
+            },
+            (Some("regs" | "r"), []) => {
+                for line in chip8.dump_state().lines().take(3) {
+                    println!("{}", line);
+                }
+            },
+            (Some("mem"), [Some(addr)] | [Some(addr), Some(_)]) => {
+                let length: u16 = args.get(1).copied().flatten().unwrap_or(DEFAULT_MEM_LENGTH);
+                print_memory(chip8, *addr, length);
+            },
+            (Some("disas"), [] | [Some(_)] | [Some(_), Some(_)]) => {
+                let addr: u16 = args.first().copied().flatten().unwrap_or(chip8.pc());
+                let count: u16 = args.get(1).copied().flatten().unwrap_or(DEFAULT_DISAS_COUNT);
+                match chip8.disassemble(addr, count) {
+                    Ok(listing) => print!("{}", listing),
+                    Err(e) => println!("{}", e),
+                }
+            },
+            (Some("poke"), [Some(addr), Some(value)]) => {
+                match u8::try_from(*value) {
+                    Ok(value) => {
+                        if let Err(e) = chip8.poke(*addr, value) {
+                            println!("{}", e);
+                        }
+                    },
+                    Err(_) => println!("Value 0x{:X} doesn't fit in a byte.", value),
+                }
+            },
+            (Some("sprite"), [] | [Some(_)]) => {
+                let height: u16 = args.first().copied().flatten().unwrap_or(DEFAULT_SPRITE_HEIGHT);
+                match u8::try_from(height) {
+                    Ok(height) => match chip8.sprite_preview(height) {
+                        Ok(preview) => print!("{}", preview),
+                        Err(e) => println!("{}", e),
+                    },
+                    Err(_) => println!("Height {} is too tall.", height),
+                }
+            },
+            (Some("grab"), [Some(x), Some(y), Some(width), Some(height)]) => {
+                let sprites: Vec<Vec<u8>> =
+                    chip8.screen_sprites(*x as usize, *y as usize, *width as usize, *height as usize);
+                for sprite in sprites {
+                    let bytes: Vec<String> = sprite.iter().map(|bits| format!("0x{:02X}", bits)).collect();
+                    println!("{}", bytes.join(" "));
+                }
+            },
+            (Some("quit" | "q"), []) => return Ok(false),
+            (Some(command), _) => println!("Can't run '{}' with those arguments; type 'help' for commands.", command),
+        }
+
+        Ok(true)
+    }
+}
+
+/// Runs up to `count` instructions, listing each, then shows the next one.
+fn step(chip8: &mut Chip8, count: u16) {
+    for _ in 0 .. count {
+        match chip8.step_traced() {
+            Ok(Some(step)) => print_step(&step),
+            Ok(None) => break,
+            Err(e) => {
+                println!("Stopped: {}", e);
+                break;
+            },
+        }
+    }
+    print_next(chip8);
+}
+
+/// Prints an instruction that ran and what it changed, e.g. "0200: 7102  ADD V1, #02  V1 04->06".
+fn print_step(step: &StepResult) {
+    let mut changes: Vec<String> = step.changed_registers.iter()
+        .map(|(reg, old, new)| format!("V{:X} {:02X}->{:02X}", reg, old, new))
+        .collect();
+    changes.extend(step.changed_memory.iter().map(|(addr, old, new)| format!("[{:04X}] {:02X}->{:02X}", addr, old, new)));
+    changes.extend(step.changed_flags.iter().map(|(flag, old, new)| format!("R{} {:02X}->{:02X}", flag, old, new)));
+    let mnemonic: String = step.mnemonic.clone().unwrap_or_else(|| "???".to_string());
+    let line: String = format!("{:04X}: {:04X}  {}  {}", step.pc_before, step.opcode, mnemonic, changes.join(" "));
+    println!("{}", line.trim_end());
+}
+
+/// Prints the instruction about to run.
+fn print_next(chip8: &Chip8) {
+    match chip8.disassemble(chip8.pc(), 1) {
+        Ok(listing) => print!("{}", listing),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Prints a hex dump of memory, 16 bytes per line, stopping at the end of RAM.
+fn print_memory(chip8: &Chip8, addr: u16, length: u16) {
+    for line_start in (0 .. length).step_by(16) {
+        let line_addr: u16 = addr.wrapping_add(line_start);
+        let bytes: Vec<String> = (line_addr .. line_addr.saturating_add((length - line_start).min(16)))
+            .map_while(|byte_addr| chip8.peek(byte_addr).ok())
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        if bytes.is_empty() {
+            break;
+        }
+        println!("{:04X}: {}", line_addr, bytes.join(" "));
+    }
+}
+
+/// Parses a decimal number, or a hex number with a 0x prefix.
+fn parse_number(text: &str) -> Option<u16> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok().filter(|_| hex.bytes().all(|c| c.is_ascii_hexdigit())),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_over_runs_calls_until_they_return() {
+        let mut chip8: Chip8 = Chip8::new();
+        // Call a subroutine that sets V1 and calls another, set V0, then loop
+        chip8.load_rom_hex("2206 6001 1204 6105 220C 00EE 6207 00EE").unwrap();
+        let mut debugger: Debugger = Debugger::new();
+
+        debugger.step_over(&mut chip8);
+        assert!(!debugger.is_paused());
+        let mut cycles: usize = 0;
+        while cycles < 100 {
+            chip8.cycle().unwrap();
+            cycles += 1;
+            if debugger.check_breakpoint(&chip8) {
+                break;
+            }
+        }
+        assert_eq!(cycles, 6);
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(chip8.sp(), 0);
+        assert_eq!(&chip8.reg_v()[0 .. 3], &[0, 5, 7]);
+
+        // Anything else is a single step
+        debugger.step_over(&mut chip8);
+        assert!(debugger.is_paused());
+        assert_eq!(chip8.pc(), 0x204);
+        assert_eq!(chip8.reg_v()[0], 1);
+    }
+}
@@ -0,0 +1,64 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: error.rs                           //
+// Description: Interpreter error handling. //
+// ---------------------------------------- //
+
+use std::fmt;
+use std::io::Error;
+
+/// Represents an error raised by the interpreter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// A bounded run hit its cycle limit before finishing.
+    CycleLimitReached(usize),
+    /// The program counter pointed at an odd address (strict mode only).
+    MisalignedProgramCounter(u16),
+    /// A memory access fell outside of RAM (strict mode only).
+    AddressOutOfBounds(u16),
+    /// A subroutine call exceeded the stack depth (strict mode only).
+    StackOverflow,
+    /// A subroutine return happened with an empty stack (strict mode only).
+    StackUnderflow,
+    /// A 0NNN machine language call was encountered (strict mode only).
+    UnsupportedInstruction(u16),
+    /// An undefined opcode was encountered (strict mode only).
+    UnknownInstruction(u16),
+    /// FX29 was asked for a font digit above 0xF (strict mode only).
+    InvalidFontDigit(u8),
+    /// EX9E or EXA1 was asked about a key above 0xF (strict mode only).
+    InvalidKey(u8),
+    /// A write targeted the font region while it is protected (strict mode only).
+    ProtectedWrite(u16),
+    /// A framebuffer copy was given an output buffer of the wrong length.
+    InvalidBufferLength(usize),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::CycleLimitReached(cycles) => write!(f, "Cycle limit of {} reached.", cycles),
+            Chip8Error::MisalignedProgramCounter(pc) => write!(f, "Program counter misaligned at 0x{:04X}.", pc),
+            Chip8Error::AddressOutOfBounds(addr) => write!(f, "Memory address 0x{:04X} is out of bounds.", addr),
+            Chip8Error::StackOverflow => write!(f, "Stack overflowed."),
+            Chip8Error::StackUnderflow => write!(f, "Stack underflowed."),
+            Chip8Error::UnsupportedInstruction(opcode) => write!(f, "Unsupported instruction: 0x{:04X}", opcode),
+            Chip8Error::UnknownInstruction(opcode) => write!(f, "Unknown instruction: 0x{:04X}", opcode),
+            Chip8Error::InvalidFontDigit(digit) => write!(f, "Font digit 0x{:02X} is greater than 0xF.", digit),
+            Chip8Error::InvalidKey(key) => write!(f, "Key 0x{:02X} is greater than 0xF.", key),
+            Chip8Error::ProtectedWrite(addr) => write!(f, "Write to protected font memory at 0x{:04X}.", addr),
+            Chip8Error::InvalidBufferLength(len) => write!(f, "Buffer length {} fits neither one byte nor one bit per pixel.", len),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+impl From<Chip8Error> for Error {
+    fn from(error: Chip8Error) -> Error {
+        Error::other(error)
+    }
+}
@@ -0,0 +1,230 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: ffi.rs                             //
+// Description: C ABI for embedding the     //
+//              interpreter.                //
+// ---------------------------------------- //
+
+use crate::chip8::*;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// The call succeeded.
+pub const CHIP8_OK: i32 = 0;
+/// A required pointer was null.
+pub const CHIP8_ERR_NULL: i32 = -1;
+/// An argument was out of range, such as a key above 0xF or a short buffer.
+pub const CHIP8_ERR_INVALID_ARGUMENT: i32 = -2;
+/// The ROM could not be loaded, usually because it's too large.
+pub const CHIP8_ERR_LOAD: i32 = -3;
+/// The interpreter raised an error while executing.
+pub const CHIP8_ERR_EXECUTION: i32 = -4;
+/// The interpreter panicked; the instance should be destroyed.
+pub const CHIP8_ERR_PANIC: i32 = -5;
+
+/// Creates a new interpreter. Free it with `chip8_destroy`.
+#[no_mangle]
+pub extern "C" fn chip8_create() -> *mut Chip8 {
+    Box::into_raw(Box::new(Chip8::new()))
+}
+
+/// Destroys an interpreter created by `chip8_create`.
+///
+/// # Safety
+/// `chip8` must be null or a pointer returned by `chip8_create` that hasn't been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Loads `len` bytes of ROM into memory at the entry point.
+///
+/// # Safety
+/// `chip8` must be a live interpreter and `rom` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom_bytes(chip8: *mut Chip8, rom: *const u8, len: usize) -> i32 {
+    if chip8.is_null() || rom.is_null() {
+        return CHIP8_ERR_NULL;
+    }
+
+    match (*chip8).load_rom_bytes(slice::from_raw_parts(rom, len)) {
+        Ok(()) => CHIP8_OK,
+        Err(_) => CHIP8_ERR_LOAD,
+    }
+}
+
+/// Runs a single instruction.
+///
+/// # Safety
+/// `chip8` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_cycle(chip8: *mut Chip8) -> i32 {
+    if chip8.is_null() {
+        return CHIP8_ERR_NULL;
+    }
+
+    match panic::catch_unwind(AssertUnwindSafe(|| (*chip8).step_instruction())) {
+        Ok(Ok(())) => CHIP8_OK,
+        Ok(Err(_)) => CHIP8_ERR_EXECUTION,
+        Err(_) => CHIP8_ERR_PANIC,
+    }
+}
+
+/// Decrements the delay and sound timers. Call this at 60Hz.
+///
+/// # Safety
+/// `chip8` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(chip8: *mut Chip8) -> i32 {
+    if chip8.is_null() {
+        return CHIP8_ERR_NULL;
+    }
+
+    (*chip8).tick_timers();
+    CHIP8_OK
+}
+
+/// Sets whether a key (0x0-0xF) is pressed.
+///
+/// # Safety
+/// `chip8` must be a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(chip8: *mut Chip8, key: u8, pressed: bool) -> i32 {
+    if chip8.is_null() {
+        return CHIP8_ERR_NULL;
+    }
+    if key > 0xF {
+        return CHIP8_ERR_INVALID_ARGUMENT;
+    }
+
+    (*chip8).set_key(key, pressed);
+    CHIP8_OK
+}
+
+/// Copies the screen into `out` as one byte per pixel (1 on, 0 off), row-major.
+/// `len` must be at least `chip8_screen_width(chip8) * chip8_screen_height(chip8)`.
+///
+/// # Safety
+/// `chip8` must be a live interpreter and `out` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_copy_framebuffer(chip8: *const Chip8, out: *mut u8, len: usize) -> i32 {
+    if chip8.is_null() || out.is_null() {
+        return CHIP8_ERR_NULL;
+    }
+
+    let chip8: &Chip8 = &*chip8;
+    let pixels: usize = chip8.width() * chip8.height();
+    if len < pixels {
+        return CHIP8_ERR_INVALID_ARGUMENT;
+    }
+
+    let out: &mut [u8] = slice::from_raw_parts_mut(out, pixels);
+    match chip8.copy_framebuffer_into(out) {
+        Ok(()) => CHIP8_OK,
+        Err(_) => CHIP8_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Gets the current screen width in pixels, or 0 if `chip8` is null.
+///
+/// # Safety
+/// `chip8` must be null or a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_screen_width(chip8: *const Chip8) -> usize {
+    if chip8.is_null() {
+        return 0;
+    }
+
+    (*chip8).width()
+}
+
+/// Gets the current screen height in pixels, or 0 if `chip8` is null.
+///
+/// # Safety
+/// `chip8` must be null or a live interpreter.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_screen_height(chip8: *const Chip8) -> usize {
+    if chip8.is_null() {
+        return 0;
+    }
+
+    (*chip8).height()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ptr;
+
+    #[test]
+    fn null_handles_are_rejected() {
+        let rom: [u8; 2] = [0x12, 0x00];
+        let mut out: [u8; 1] = [0];
+        unsafe {
+            chip8_destroy(ptr::null_mut());
+            assert_eq!(chip8_load_rom_bytes(ptr::null_mut(), rom.as_ptr(), rom.len()), CHIP8_ERR_NULL);
+            assert_eq!(chip8_cycle(ptr::null_mut()), CHIP8_ERR_NULL);
+            assert_eq!(chip8_tick_timers(ptr::null_mut()), CHIP8_ERR_NULL);
+            assert_eq!(chip8_set_key(ptr::null_mut(), 0x1, true), CHIP8_ERR_NULL);
+            assert_eq!(chip8_copy_framebuffer(ptr::null(), out.as_mut_ptr(), out.len()), CHIP8_ERR_NULL);
+            assert_eq!(chip8_screen_width(ptr::null()), 0);
+            assert_eq!(chip8_screen_height(ptr::null()), 0);
+
+            // Null buffers are rejected too, even with a live interpreter
+            let chip8: *mut Chip8 = chip8_create();
+            assert_eq!(chip8_load_rom_bytes(chip8, ptr::null(), 2), CHIP8_ERR_NULL);
+            assert_eq!(chip8_copy_framebuffer(chip8, ptr::null_mut(), 64 * 32), CHIP8_ERR_NULL);
+            chip8_destroy(chip8);
+        }
+    }
+
+    #[test]
+    fn bad_arguments_return_error_codes() {
+        unsafe {
+            let chip8: *mut Chip8 = chip8_create();
+            assert_eq!(chip8_set_key(chip8, 0xF, true), CHIP8_OK);
+            assert_eq!(chip8_set_key(chip8, 0x10, true), CHIP8_ERR_INVALID_ARGUMENT);
+            assert_eq!(chip8_set_key(chip8, 0xFF, false), CHIP8_ERR_INVALID_ARGUMENT);
+
+            // A ROM too large for memory fails to load
+            let too_large: Vec<u8> = vec![0; MAX_MEMORY_SIZE];
+            assert_eq!(chip8_load_rom_bytes(chip8, too_large.as_ptr(), too_large.len()), CHIP8_ERR_LOAD);
+
+            // A buffer one byte short of the screen is rejected and left alone
+            let pixels: usize = chip8_screen_width(chip8) * chip8_screen_height(chip8);
+            let mut out: Vec<u8> = vec![7; pixels];
+            assert_eq!(chip8_copy_framebuffer(chip8, out.as_mut_ptr(), pixels - 1), CHIP8_ERR_INVALID_ARGUMENT);
+            assert!(out.iter().all(|byte| *byte == 7));
+            chip8_destroy(chip8);
+        }
+    }
+
+    #[test]
+    fn rom_runs_and_draws_through_the_c_abi() {
+        // Draw the "0" glyph at (0, 0), then loop
+        let rom: [u8; 6] = [0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04];
+        unsafe {
+            let chip8: *mut Chip8 = chip8_create();
+            assert_eq!(chip8_load_rom_bytes(chip8, rom.as_ptr(), rom.len()), CHIP8_OK);
+            for _ in 0 .. 3 {
+                assert_eq!(chip8_cycle(chip8), CHIP8_OK);
+            }
+            assert_eq!(chip8_tick_timers(chip8), CHIP8_OK);
+
+            let (width, height) = (chip8_screen_width(chip8), chip8_screen_height(chip8));
+            assert_eq!((width, height), (64, 32));
+            let mut out: Vec<u8> = vec![0; width * height];
+            assert_eq!(chip8_copy_framebuffer(chip8, out.as_mut_ptr(), out.len()), CHIP8_OK);
+            assert_eq!(&out[.. 5], &[1, 1, 1, 1, 0]);
+            assert_eq!(&out[width .. width + 5], &[1, 0, 0, 1, 0]);
+            chip8_destroy(chip8);
+        }
+    }
+}
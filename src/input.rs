@@ -0,0 +1,102 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: input.rs                           //
+// Description: Keyboard to keypad mapping. //
+// ---------------------------------------- //
+
+use chip8_rust::keypad;
+
+use sdl2::keyboard::{Keycode, Scancode};
+
+/// Represents how keyboard keys are matched to the hex keypad.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InputMode {
+    /// Match by the character on the key, which depends on the keyboard layout.
+    Keycode,
+    /// Match by physical key position, regardless of layout.
+    Scancode,
+}
+
+/// Represents a built-in arrangement of keyboard keys for the hex keypad.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum KeymapPreset {
+    /// The 1234/QWER/ASDF/ZXCV block, laid out like the COSMAC VIP keypad.
+    Modern,
+    /// The numeric keypad, with digits on their own keys and A-F on the operator keys.
+    Numpad,
+}
+
+/// Converts a key event into a keypad index according to the preset and input mode.
+/// The numpad preset always matches by position, since numpad keys don't move between layouts.
+pub fn map_key(mode: InputMode, preset: KeymapPreset, keycode: Option<Keycode>, scancode: Option<Scancode>) -> Option<usize> {
+    match (preset, mode) {
+        (KeymapPreset::Numpad, _) => process_numpad(scancode?),
+        (KeymapPreset::Modern, InputMode::Keycode) => process_key(keycode?),
+        (KeymapPreset::Modern, InputMode::Scancode) => process_scancode(scancode?),
+    }
+}
+
+/// Converts a keycode into a keypad index by the character on the key.
+fn process_key(key: Keycode) -> Option<usize> {
+    // Keycodes of character keys are the characters themselves
+    char::from_u32(key.into_i32() as u32).and_then(keypad::key_for_char)
+}
+
+/// Converts a scancode into a keypad index by the character its key has on a US layout, so the physical
+/// 1234/QWER/ASDF/ZXCV block is used whatever the layout.
+fn process_scancode(key: Scancode) -> Option<usize> {
+    // Scancodes follow USB HID usage IDs, which number the letters A-Z from 4 and the digits 1-9 from 30
+    let code: u32 = key as u32;
+    let c: char = match code {
+        4 ..= 29 => char::from_u32('a' as u32 + code - 4)?,
+        30 ..= 38 => char::from_u32('1' as u32 + code - 30)?,
+        _ => return None,
+    };
+    keypad::key_for_char(c)
+}
+
+/// Converts a numpad scancode into a keypad index. Digits map to themselves, which keeps the common 2/4/6/8
+/// movement keys in a cross, and / * - + Enter . map to A-F.
+fn process_numpad(key: Scancode) -> Option<usize> {
+    match key {
+        Scancode::Kp0 =>        Some(0x0),
+        Scancode::Kp1 =>        Some(0x1),
+        Scancode::Kp2 =>        Some(0x2),
+        Scancode::Kp3 =>        Some(0x3),
+        Scancode::Kp4 =>        Some(0x4),
+        Scancode::Kp5 =>        Some(0x5),
+        Scancode::Kp6 =>        Some(0x6),
+        Scancode::Kp7 =>        Some(0x7),
+        Scancode::Kp8 =>        Some(0x8),
+        Scancode::Kp9 =>        Some(0x9),
+        Scancode::KpDivide =>   Some(0xA),
+        Scancode::KpMultiply => Some(0xB),
+        Scancode::KpMinus =>    Some(0xC),
+        Scancode::KpPlus =>     Some(0xD),
+        Scancode::KpEnter =>    Some(0xE),
+        Scancode::KpPeriod =>   Some(0xF),
+        _ =>                    None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keycodes_and_scancodes_use_the_same_block() {
+        let keycodes: [Keycode; 4] = [Keycode::Num1, Keycode::Num4, Keycode::X, Keycode::V];
+        let scancodes: [Scancode; 4] = [Scancode::Num1, Scancode::Num4, Scancode::X, Scancode::V];
+        let expected: [Option<usize>; 4] = [Some(0x1), Some(0xC), Some(0x0), Some(0xF)];
+        assert_eq!(keycodes.map(process_key), expected);
+        assert_eq!(scancodes.map(process_scancode), expected);
+
+        assert_eq!(process_key(Keycode::Num5), None);
+        assert_eq!(process_key(Keycode::F1), None);
+        assert_eq!(process_scancode(Scancode::Num0), None);
+        assert_eq!(process_scancode(Scancode::Kp1), None);
+    }
+}
@@ -55,6 +55,75 @@ impl Instruction {
 
     /// Gets the NNN immediate address of the instruction.
     pub fn nnn(&self) -> u16 {
-        (self.raw & 0x0FFF) as u16
+        self.raw & 0x0FFF
+    }
+
+    /// Decodes the instruction into an assembly mnemonic, or None if it isn't a valid instruction.
+    pub fn disassemble(&self) -> Option<String> {
+        let (x, y, n, nn, nnn) = (self.nibble2(), self.nibble3(), self.n(), self.nn(), self.nnn());
+        let text: String = match self.nibble1() {
+            0x0 => match self.raw {
+                0x00E0 => "CLS".to_string(),
+                0x00EE => "RET".to_string(),
+                _ => format!("SYS #{:03X}", nnn),
+            },
+            0x1 => format!("JP #{:03X}", nnn),
+            0x2 => format!("CALL #{:03X}", nnn),
+            0x3 => format!("SE V{:X}, #{:02X}", x, nn),
+            0x4 => format!("SNE V{:X}, #{:02X}", x, nn),
+            0x5 => match n {
+                0x0 => format!("SE V{:X}, V{:X}", x, y),
+                0x1 => format!("SGT V{:X}, V{:X}", x, y),
+                0x2 => format!("LD [I], V{:X}-V{:X}", x, y),
+                0x3 => format!("LD V{:X}-V{:X}, [I]", x, y),
+                _ => return None,
+            },
+            0x6 => format!("LD V{:X}, #{:02X}", x, nn),
+            0x7 => format!("ADD V{:X}, #{:02X}", x, nn),
+            0x8 => {
+                let op: &str = match n {
+                    0x0 => "LD",
+                    0x1 => "OR",
+                    0x2 => "AND",
+                    0x3 => "XOR",
+                    0x4 => "ADD",
+                    0x5 => "SUB",
+                    0x6 => "SHR",
+                    0x7 => "SUBN",
+                    0xE => "SHL",
+                    _ => return None,
+                };
+                format!("{} V{:X}, V{:X}", op, x, y)
+            },
+            0x9 => match n {
+                0x0 => format!("SNE V{:X}, V{:X}", x, y),
+                _ => return None,
+            },
+            0xA => format!("LD I, #{:03X}", nnn),
+            0xB => format!("JP V0, #{:03X}", nnn),
+            0xC => format!("RND V{:X}, #{:02X}", x, nn),
+            0xD => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+            0xE => match nn {
+                0x9E => format!("SKP V{:X}", x),
+                0xA1 => format!("SKNP V{:X}", x),
+                _ => return None,
+            },
+            0xF => match nn {
+                0x07 => format!("LD V{:X}, DT", x),
+                0x0A => format!("LD V{:X}, K", x),
+                0x15 => format!("LD DT, V{:X}", x),
+                0x18 => format!("LD ST, V{:X}", x),
+                0x1E => format!("ADD I, V{:X}", x),
+                0x29 => format!("LD F, V{:X}", x),
+                0x33 => format!("LD B, V{:X}", x),
+                0x55 => format!("LD [I], V{:X}", x),
+                0x65 => format!("LD V{:X}, [I]", x),
+                0x75 if x < 8 => format!("LD R, V{:X}", x),
+                0x85 if x < 8 => format!("LD V{:X}, R", x),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(text)
     }
 }
@@ -0,0 +1,36 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: keypad.rs                          //
+// Description: Keyboard layout shared by   //
+//              the front-ends.             //
+// ---------------------------------------- //
+
+/// The keyboard character for each keypad key, by key index. The 1234/QWER/ASDF/ZXCV block is laid out like the
+/// COSMAC VIP keypad, so 1 is at the top left and F at the bottom right.
+pub const QWERTY_KEYS: [char; 16] = ['x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v'];
+
+/// Converts a keyboard character into a keypad index using `QWERTY_KEYS`, ignoring case.
+pub fn key_for_char(c: char) -> Option<usize> {
+    let c: char = c.to_ascii_lowercase();
+    QWERTY_KEYS.iter().position(|key| *key == c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_map_to_the_vip_layout() {
+        let rows: [&str; 4] = ["1234", "qwer", "asdf", "zxcv"];
+        let keys: Vec<Option<usize>> = rows.iter().flat_map(|row| row.chars()).map(key_for_char).collect();
+        let expected: [usize; 16] = [0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF];
+        assert_eq!(keys, expected.map(Some));
+
+        assert_eq!(key_for_char('V'), Some(0xF));
+        assert_eq!(key_for_char('5'), None);
+        assert_eq!(key_for_char(' '), None);
+    }
+}
@@ -0,0 +1,17 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: lib.rs                             //
+// Description: Library root.               //
+// ---------------------------------------- //
+
+pub mod chip8;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod keypad;
+pub mod quirks;
+pub mod timing;
+mod instruction;
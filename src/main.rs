@@ -12,60 +12,240 @@
 //   start with something interesting :)    //
 // ---------------------------------------- //
 
-mod chip8;
-mod instruction;
+mod audio;
+#[cfg(feature = "config")]
+mod config;
+mod debugger;
+mod input;
+mod options;
+mod overlay;
+mod render;
+mod scheduler;
+mod screenshot;
+mod stats;
 
-use crate::chip8::*;
+use crate::audio::{Envelope, FADE_DURATION};
+use crate::debugger::Debugger;
+use crate::input::map_key;
+use crate::options::{Options, STDIN_ROM_PATH};
+use crate::render::*;
+use crate::scheduler::Scheduler;
+use crate::stats::FrameStats;
+use chip8_rust::chip8::*;
+use chip8_rust::quirks::Quirks;
 
 use std::env;
-use std::io::Error;
+use std::fs;
+use std::fmt::Display;
+use std::io::{self, Error, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use rodio::source::SineWave;
-use rodio::{OutputStream, Sink, Source};
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::mouse::MouseButton;
 
-/// Factor by which to scale the window up.
-const SCALE_FACTOR: u32 = 8;
 /// Calculated window width from CHIP-8 screen width.
 const WINDOW_WIDTH: u32 = SCREEN_WIDTH as u32 * SCALE_FACTOR;
 /// Calculated window height from CHIP-8 screen height.
 const WINDOW_HEIGHT: u32 = SCREEN_HEIGHT as u32 * SCALE_FACTOR;
+/// Frame rate assumed for timers and instruction budgets when running headless.
+const HEADLESS_REFRESH_RATE: u32 = 60;
 /// Sine wave frequency for sound.
 const SINE_FREQUENCY: f32 = 440.0;
-/// The number of cycles to run per display refresh.
-const TICKS_PER_REFRESH: i32 = 600;
-/// The color of "off" pixels.
-const COLOR_OFF: Color = Color::RGB(0x66, 0x10, 0x4B);
-/// The color of "on" pixels.
-const COLOR_ON: Color = Color::RGB(0xDB, 0x22, 0xA1);
+/// Click frequency when the delay timer is set, for `--debug-audio`.
+const DELAY_SET_FREQUENCY: f32 = 1760.0;
+/// Click frequency when the delay timer runs out, for `--debug-audio`.
+const DELAY_EXPIRED_FREQUENCY: f32 = 880.0;
+/// Length of a `--debug-audio` click.
+const CLICK_DURATION: Duration = Duration::from_millis(8);
+
+/// Prints a status line unless quiet mode is on.
+macro_rules! info {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            println!($($arg)*);
+        }
+    };
+}
 
 /// Main entry point.
-fn main() -> Result<(), Error> {
-    // Make a good first impression
-    println!("chip8-rust - Kai NeSmith (c) 2024");
+fn main() -> ExitCode {
+    // Print errors as plain messages rather than debug output
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
 
+/// Runs the emulator with the command line options.
+fn run() -> Result<(), Error> {
     // Load arguments
-    let rom_path: String = env::args().nth(1).expect("No ROM file provided.");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options: Options = Options::parse(args.iter().cloned())?;
+
+    // Apply saved settings for this ROM, letting the command line take precedence
+    #[cfg(feature = "config")]
+    let saved_options: Option<Options> = config::apply_saved_args(&options, &args);
+    #[cfg(feature = "config")]
+    let loaded_saved: bool = saved_options.is_some();
+    #[cfg(feature = "config")]
+    let options: Options = saved_options.unwrap_or(options);
 
+    // Make a good first impression
+    info!(options.quiet, "chip8-rust - Kai NeSmith (c) 2024");
+    #[cfg(feature = "config")]
+    if loaded_saved {
+        info!(options.quiet, "Config:\t\tLoaded saved settings");
+    }
+
+    // Show how this run differs from plain CHIP-8
+    let changed_quirks: Vec<&str> = Quirks::default().diff(&options.quirks);
+    if !changed_quirks.is_empty() {
+        info!(options.quiet, "Quirks:\t\tChanged {}", changed_quirks.join(", "));
+    }
+
+    // Make CXNN repeatable if asked to
+    if let Some(seed) = options.seed {
+        fastrand::seed(seed);
+    }
+
+    // Initialize Chip8 system
+    let mut builder: Chip8Builder = Chip8::builder()
+        .strict(options.strict)
+        .protect_font(options.protect_font)
+        .detect_idle(true)
+        .skip_delay_loops(options.skip_delay_loops)
+        .extended_opcodes(options.extended_opcodes)
+        .await_key_mode(options.await_key_mode)
+        .quirks(options.quirks);
+    if let Some(behavior) = options.machine_code {
+        builder = builder.machine_code(behavior);
+    }
+    if let Some(depth) = options.stack_depth {
+        builder = builder.stack_depth(depth);
+    }
+    if let Some(threshold) = options.sound_threshold {
+        builder = builder.sound_threshold(threshold);
+    }
+    if options.debug {
+        builder = builder.undo_depth(debugger::UNDO_DEPTH);
+    }
+    #[cfg(feature = "config")]
+    if options.persist_flags {
+        if let Some(flags) = config::load_flags(&options.rom_path) {
+            info!(options.quiet, "RPL flags:\tLoaded saved flags");
+            builder = builder.rpl_flags(flags);
+        }
+    }
+    let mut chip8: Chip8 = builder.build();
+
+    // Save the RPL flags whenever FX75 changes them, as SUPER-CHIP kept them across runs
+    #[cfg(feature = "config")]
+    if options.persist_flags {
+        let rom_path: String = options.rom_path.clone();
+        chip8.set_rpl_callback(move |flags| {
+            if let Err(e) = config::save_flags(&rom_path, flags) {
+                eprintln!("Warning: Failed to save RPL flags: {}", e);
+            }
+        });
+    }
+    if options.rom_path == STDIN_ROM_PATH {
+        info!(options.quiet, "ROM:\t\tReading from stdin");
+        let mut rom_bytes: Vec<u8> = Vec::new();
+        io::stdin().read_to_end(&mut rom_bytes)?;
+        chip8.load_rom_bytes(&rom_bytes)?;
+    }
+    else {
+        chip8.load_rom(&options.rom_path)?;
+    }
+
+    // Describe the ROM instead of running it, if asked to
+    if options.info {
+        print!("{}", chip8.rom_info());
+        return Ok(());
+    }
+
+    // Run in a window, or as fast as possible without one
+    let run_mode = |chip8: &mut Chip8| if options.headless { run_headless(chip8, &options) } else { run_windowed(chip8, &options) };
+    let result: Result<u64, Error> = if options.dump_on_panic {
+        // Print the machine state after the panic message, then carry on unwinding as usual
+        match panic::catch_unwind(AssertUnwindSafe(|| run_mode(&mut chip8))) {
+            Ok(result) => result,
+            Err(payload) => {
+                eprintln!("State at panic:\n{}", chip8.dump_state());
+                panic::resume_unwind(payload);
+            },
+        }
+    }
+    else {
+        run_mode(&mut chip8)
+    };
+
+    // Save memory and registers as the program left them, even if it failed, reporting the failure first
+    let dumped: Result<(), Error> = write_dumps(&chip8, &options);
+    let executed: u64 = result?;
+    dumped?;
+    if options.run_cycles.is_some() {
+        println!("Cycles:\t\t{} run", executed);
+    }
+
+    // Report which opcodes ran
+    #[cfg(feature = "profiling")]
+    if options.coverage {
+        print!("{}", chip8.coverage_report());
+    }
+
+    // Save the final frame for comparison against a known-good image
+    if let Some(path) = &options.screenshot_path {
+        screenshot::save_png(&chip8, path)?;
+        info!(options.quiet, "Screenshot:\t{}", path);
+    }
+
+    // Remember this ROM's settings for next time
+    #[cfg(feature = "config")]
+    config::save_args(&options.rom_path, &options.to_args(chip8.quirks()))?;
+
+    Ok(())
+}
+
+/// Writes the memory and register dumps asked for on the command line.
+fn write_dumps(chip8: &Chip8, options: &Options) -> Result<(), Error> {
+    if let Some(path) = &options.dump_memory_path {
+        fs::write(path, chip8.memory())?;
+        info!(options.quiet, "Memory dump:\t{}", path);
+    }
+    if let Some(path) = &options.dump_regs_path {
+        let mut text: String = chip8.dump_state().lines().take(3).collect::<Vec<&str>>().join("\n");
+        text.push('\n');
+        fs::write(path, text)?;
+        info!(options.quiet, "Register dump:\t{}", path);
+    }
+    Ok(())
+}
+
+/// Runs the interpreter in a window with sound and keyboard input until the user quits, returning the number
+/// of instructions run.
+fn run_windowed(chip8: &mut Chip8, options: &Options) -> Result<u64, Error> {
     // Initialize SDL window
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window(
-            "chip8-rust",
-            WINDOW_WIDTH,
-            WINDOW_HEIGHT,
-        )
-        .position_centered()
-        .build()
-        .unwrap();
-    println!("Screen size:\t{} x {}", SCREEN_WIDTH, SCREEN_HEIGHT);
-    println!("Window size:\t{} x {} (x{})", WINDOW_WIDTH, WINDOW_HEIGHT, SCALE_FACTOR);
+    let sdl_context = sdl2::init().map_err(context("Failed to initialize SDL"))?;
+    let video_subsystem = sdl_context.video().map_err(context("Failed to initialize video"))?;
+    let mut window_builder = video_subsystem.window("chip8-rust", WINDOW_WIDTH, WINDOW_HEIGHT);
+    window_builder.position_centered();
+    if options.integer_scale {
+        window_builder.resizable();
+    }
+    let window = window_builder.build().map_err(context("Failed to create window"))?;
+    info!(options.quiet, "Screen size:\t{} x {}", chip8.width(), chip8.height());
+    info!(options.quiet, "Window size:\t{} x {} (x{})", WINDOW_WIDTH, WINDOW_HEIGHT, SCALE_FACTOR);
 
     // Initialize drawing canvas
     let mut canvas = window
@@ -73,31 +253,104 @@ fn main() -> Result<(), Error> {
         .present_vsync()
         .accelerated()
         .build()
-        .unwrap();
+        .map_err(context("Failed to create canvas"))?;
+
+    // Keep drawing at the original window size, letting SDL scale it by whole numbers and letterbox the rest
+    if options.integer_scale {
+        canvas.set_logical_size(WINDOW_WIDTH, WINDOW_HEIGHT).map_err(context("Failed to set logical size"))?;
+        canvas.set_integer_scale(true).map_err(context("Failed to set integer scale"))?;
+        info!(options.quiet, "Scaling:\tInteger multiples only (resizable)");
+    }
     canvas.set_draw_color(COLOR_OFF);
     canvas.clear();
     canvas.present();
 
-    // Initialize audio system
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let source = SineWave::new(SINE_FREQUENCY).repeat_infinite();
-    sink.pause();
-    sink.append(source);
-    println!("Sound mode:\tSine @ {} Hz", SINE_FREQUENCY);
+    // Initialize screen renderer
+    let texture_creator = canvas.texture_creator();
+    let mut renderer: Renderer = Renderer::new(options.render_mode, &texture_creator)
+        .map_err(context("Failed to create renderer"))?;
+
+    // Initialize audio system, running silently if there's no output device
+    let gate: Option<Arc<AtomicBool>> = (!options.no_audio_fade).then(|| Arc::new(AtomicBool::new(false)));
+    let (_stream, stream_handle, sink): (Option<OutputStream>, Option<OutputStreamHandle>, Option<Sink>) = if options.no_audio {
+        info!(options.quiet, "Sound mode:\tDisabled");
+        (None, None, None)
+    }
+    else {
+        match init_audio(gate.clone()) {
+            Ok((stream, stream_handle, sink)) => {
+                info!(options.quiet, "Sound mode:\tSine @ {} Hz", SINE_FREQUENCY);
+                if gate.is_some() {
+                    info!(options.quiet, "Sound fade:\t{} ms", FADE_DURATION.as_millis());
+                }
+                (Some(stream), Some(stream_handle), Some(sink))
+            },
+            Err(e) => {
+                eprintln!("Warning: Running without sound: {}", e);
+                (None, None, None)
+            },
+        }
+    };
 
     // Initialize event pump
-    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut event_pump = sdl_context.event_pump().map_err(context("Failed to create event pump"))?;
 
     // Calculate needed tick rate based on display refresh rate
-    let refresh_rate: i32 = video_subsystem.current_display_mode(0).unwrap().refresh_rate;
-    println!("Refresh rate:\t{} Hz", refresh_rate);
-    let ticks_per_frame: usize = (TICKS_PER_REFRESH / refresh_rate).try_into().unwrap();
-    println!("Ticks/frame:\t{}", ticks_per_frame);
+    let refresh_rate: i32 = video_subsystem.current_display_mode(0)
+        .map_err(context("Failed to read display mode"))?
+        .refresh_rate;
+    info!(options.quiet, "Refresh rate:\t{} Hz", refresh_rate);
+    let mut scheduler: Scheduler = Scheduler::new(options.speed, refresh_rate as u32, options.adaptive_speed, options.quiet);
+    let mut stats: Option<FrameStats> = options.stats.map(FrameStats::new);
+    info!(options.quiet, "Ticks/frame:\t{}", scheduler.ticks_per_frame());
 
-    // Initialize Chip8 system
-    let mut chip8: Chip8 = Chip8::new();
-    chip8.load_rom(&rom_path)?;
+    // Toggle sound output as the sound timer crosses the audible threshold, fading through the envelope if there is one
+    if let Some(sink) = sink {
+        match gate {
+            Some(gate) => chip8.set_sound_callback(move |audible| gate.store(audible, Ordering::Relaxed)),
+            None => chip8.set_sound_callback(move |audible| {
+                if audible {
+                    sink.play();
+                }
+                else {
+                    sink.pause();
+                }
+            }),
+        }
+    }
+
+    // Click as the delay timer is set and runs out, so a ROM's timing loops can be heard
+    if let Some(stream_handle) = stream_handle.filter(|_| options.debug_audio) {
+        info!(options.quiet, "Debug audio:\tClicking on delay timer set ({} Hz) and expiry ({} Hz)", DELAY_SET_FREQUENCY, DELAY_EXPIRED_FREQUENCY);
+        chip8.set_delay_callback(move |event| {
+            let frequency: f32 = match event {
+                DelayEvent::Set => DELAY_SET_FREQUENCY,
+                DelayEvent::Expired => DELAY_EXPIRED_FREQUENCY,
+            };
+            // A missed click isn't worth stopping for
+            let _ = stream_handle.play_raw(SineWave::new(frequency).take_duration(CLICK_DURATION).amplify(0.5));
+        });
+    }
+
+    // Budget already used up by instructions that overran the previous frame
+    let mut spent: usize = 0;
+    let mut executed: u64 = 0;
+
+    // Debug overlay toggles
+    let mut show_grid: bool = false;
+    let mut show_keypad: bool = options.mouse_keypad;
+
+    // The keypad key held down with the mouse, if any
+    let mut mouse_key: Option<u8> = None;
+
+    // Pausing from the keyboard, starting paused if asked to
+    let mut paused: bool = options.start_paused;
+    if paused {
+        info!(options.quiet, "Paused:\t\tF8 resumes, F9 steps");
+    }
+
+    // Console debugger, if asked for
+    let mut debugger: Option<Debugger> = if options.debug { Some(Debugger::new()) } else { None };
 
     // Execution loop
     'execute: loop {
@@ -105,82 +358,276 @@ fn main() -> Result<(), Error> {
             match event {
                 Event::Quit { .. } |
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    println!("Quitting.");
+                    info!(options.quiet, "Quitting.");
                     break 'execute;
                 },
-                Event::KeyDown { keycode: Some(key), .. } => {
-                    if let Some(key_val) = process_key(key) {
-                        chip8.keypad[key_val] = true;
+                Event::KeyDown { keycode: Some(Keycode::F1), repeat: false, .. } => {
+                    show_grid = !show_grid;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F2), repeat: false, .. } => {
+                    let dump: String = chip8.dump_state();
+                    println!("{}", dump);
+                    if let Err(e) = video_subsystem.clipboard().set_clipboard_text(&dump) {
+                        eprintln!("Could not copy state to clipboard: {}", e);
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::F3), repeat: false, .. } => {
+                    chip8.quirks_mut().shift = !chip8.quirks().shift;
+                    println!("Quirks:\t\t{:?}", chip8.quirks());
+                },
+                Event::KeyDown { keycode: Some(Keycode::F4), repeat: false, .. } => {
+                    let increment: bool = !chip8.quirks().store_increment;
+                    chip8.quirks_mut().store_increment = increment;
+                    chip8.quirks_mut().load_increment = increment;
+                    println!("Quirks:\t\t{:?}", chip8.quirks());
+                },
+                Event::KeyDown { keycode: Some(Keycode::F5), repeat: false, .. } => {
+                    chip8.quirks_mut().wrap_x = !chip8.quirks().wrap_x;
+                    println!("Quirks:\t\t{:?}", chip8.quirks());
+                },
+                Event::KeyDown { keycode: Some(Keycode::F6), repeat: false, .. } => {
+                    chip8.quirks_mut().wrap_y = !chip8.quirks().wrap_y;
+                    println!("Quirks:\t\t{:?}", chip8.quirks());
+                },
+                Event::KeyDown { keycode: Some(Keycode::F7), repeat: false, .. } => {
+                    show_keypad = !show_keypad;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F8), repeat: false, .. } => {
+                    paused = !paused;
+                    info!(options.quiet, "{}", if paused { "Paused." } else { "Resumed." });
+                },
+                Event::KeyDown { keycode: Some(Keycode::F9), .. } if paused => {
+                    if let Ok(listing) = chip8.disassemble(chip8.pc(), 1) {
+                        print!("{}", listing);
+                    }
+                    chip8.step_instruction()?;
+                    executed += 1;
+                },
+                Event::KeyDown { keycode: Some(keycode @ (Keycode::Equals | Keycode::Plus | Keycode::Minus)), .. } => {
+                    let step: isize = if keycode == Keycode::Minus { -1 } else { 1 };
+                    let ticks: usize = scheduler.adjust_ticks(step);
+                    info!(options.quiet, "Speed:\t\t{} ticks/frame ({} IPS)", ticks, scheduler.effective_ips());
+                    let title: String = format!("chip8-rust ({} IPS)", scheduler.effective_ips());
+                    canvas.window_mut().set_title(&title).map_err(context("Failed to set window title"))?;
+                },
+                Event::KeyDown { keycode, scancode, .. } => {
+                    if let Some(key_val) = map_key(options.input_mode, options.keymap_preset, keycode, scancode) {
+                        chip8.set_key(key_val as u8, true);
+                    }
+                },
+                Event::KeyUp { keycode, scancode, .. } => {
+                    if let Some(key_val) = map_key(options.input_mode, options.keymap_preset, keycode, scancode) {
+                        chip8.set_key(key_val as u8, false);
+                    }
+                },
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } if options.mouse_keypad && show_keypad => {
+                    mouse_key = overlay::keypad_key_at(x, y);
+                    if let Some(key) = mouse_key {
+                        chip8.set_key(key, true);
                     }
                 },
-                Event::KeyUp { keycode: Some(key), .. } => {
-                    if let Some(key_val) = process_key(key) {
-                        chip8.keypad[key_val] = false;
+                Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } => {
+                    if let Some(key) = mouse_key.take() {
+                        chip8.set_key(key, false);
                     }
                 },
                 _ => (),
             }
         }
 
-        // Cycle the interpreter
-        for _ in 0 .. ticks_per_frame {
-            chip8.cycle();
+        // Hand control to the debugger console while paused, redrawing after each command
+        if let Some(debugger) = debugger.as_mut() {
+            if debugger.is_paused() && !debugger.prompt(chip8)? {
+                break 'execute;
+            }
         }
-        chip8.cycle_special_regs();
 
-        // Adjust sound output accordingly
-        if chip8.reg_sound > 1 && sink.is_paused() {
-            sink.play();
+        // Run this frame's share of instructions
+        let budget: usize = scheduler.ticks_per_frame();
+        run_frame(chip8, options, budget, &mut spent, &mut executed, paused, &mut debugger)?;
+
+        // Draw results
+        let changed: bool = chip8.take_display_dirty();
+        if options.integer_scale {
+            // The texture strategies only cover the screen area, so fill the letterbox bars first
+            canvas.set_draw_color(COLOR_OFF);
+            canvas.clear();
         }
-        else if chip8.reg_sound <= 1 && !sink.is_paused() {
-            sink.pause();
+        renderer.draw(chip8, &mut canvas, changed).map_err(context("Failed to draw screen"))?;
+        if show_grid {
+            overlay::draw_grid(&mut canvas).map_err(context("Failed to draw grid"))?;
+        }
+        if show_keypad {
+            overlay::draw_keypad(&mut canvas, &chip8.keypad_state()).map_err(context("Failed to draw keypad"))?;
         }
+        canvas.present();
 
-        // Draw results
-        draw_screen(&chip8, &mut canvas);
+        // Stop once the final frame is on screen, if asked to
+        if options.exit_on_idle && chip8.status() == Status::Idle {
+            break 'execute;
+        }
+        if cycle_limit_reached(options, executed) {
+            break 'execute;
+        }
+
+        // Adapt the instruction budget, showing the effective speed while throttled
+        if scheduler.end_frame() {
+            let title: String = format!("chip8-rust ({} IPS)", scheduler.effective_ips());
+            canvas.window_mut().set_title(&title).map_err(context("Failed to set window title"))?;
+        }
+        if let Some(stats) = stats.as_mut() {
+            stats.end_frame(executed);
+        }
     }
 
-    Ok(())
+    Ok(executed)
 }
 
-/// Updates the screen
-fn draw_screen(chip8: &Chip8, canvas: &mut Canvas<Window>) {
-    // Clear canvas
-    canvas.set_draw_color(COLOR_OFF);
-    canvas.clear();
+/// Runs the interpreter without a window, sound or input, as fast as possible until the program goes idle, halts
+/// or waits for a key (or the cycle limit is reached), returning the number of instructions run.
+/// Nothing here touches SDL or the audio device, so automated runs start instantly. Keys can't be pressed, so FX0A
+/// would wait forever and ends the run instead.
+fn run_headless(chip8: &mut Chip8, options: &Options) -> Result<u64, Error> {
+    info!(options.quiet, "Mode:\t\tHeadless");
+    let scheduler: Scheduler = Scheduler::new(options.speed, HEADLESS_REFRESH_RATE, false, options.quiet);
+    let budget: usize = scheduler.ticks_per_frame();
+    let mut spent: usize = 0;
+    let mut executed: u64 = 0;
+    let mut stats: Option<FrameStats> = options.stats.map(FrameStats::new);
+    while !chip8.status().is_stopped() && chip8.status() != Status::Waiting && !cycle_limit_reached(options, executed) {
+        run_frame(chip8, options, budget, &mut spent, &mut executed, false, &mut None)?;
+        if let Some(stats) = stats.as_mut() {
+            stats.end_frame(executed);
+        }
+    }
+    if chip8.status() == Status::Waiting {
+        info!(options.quiet, "Program finished (waiting for a key at 0x{:04X}, which can't be pressed headless).", chip8.pc());
+    }
+    Ok(executed)
+}
+
+/// Checks whether `--run-cycles` instructions have run.
+fn cycle_limit_reached(options: &Options, executed: u64) -> bool {
+    options.run_cycles.is_some_and(|limit| executed >= limit)
+}
+
+/// Runs one frame's worth of instructions, stopping early at the cycle limit, and ticks the timers.
+/// Does nothing while paused, either by the user or by the debugger.
+fn run_frame(
+    chip8: &mut Chip8,
+    options: &Options,
+    budget: usize,
+    spent: &mut usize,
+    executed: &mut u64,
+    paused: bool,
+    debugger: &mut Option<Debugger>,
+) -> Result<(), Error> {
+    let paused: bool = paused || debugger.as_ref().is_some_and(|debugger| debugger.is_paused());
 
-    // Draw in rects as pixels
-    canvas.set_draw_color(COLOR_ON);
-    for (i, pixel) in chip8.graphics_buffer.iter().enumerate() {
-        if *pixel {
-            let x = (i % (SCREEN_WIDTH as usize)) as u32;
-            let y = (i / (SCREEN_WIDTH as usize)) as u32;
-            let rect = Rect::new((x * SCALE_FACTOR) as i32, (y * SCALE_FACTOR) as i32, SCALE_FACTOR, SCALE_FACTOR);
-            canvas.fill_rect(rect).unwrap();
+    // Cycle the interpreter, stopping for good once the program spins on a self-jump
+    // With a cost table, the budget is spent by cost and any overrun comes out of the next frame
+    let was_stopped: bool = chip8.status().is_stopped();
+    while !paused && *spent < budget && !cycle_limit_reached(options, *executed) {
+        if let Err(e) = chip8.step_instruction() {
+            match debugger.as_mut() {
+                Some(debugger) => {
+                    debugger.pause_on_error(&e);
+                    break;
+                },
+                None => return Err(e.into()),
+            }
+        }
+        else {
+            // Count the instruction even if it's the one that stops the program or lands on a breakpoint
+            *executed += 1;
+            *spent += match options.cost_table {
+                Some(table) => table.cost(chip8.last_instruction()) as usize,
+                None => 1,
+            };
+        }
+        if chip8.status().is_stopped() {
+            break;
+        }
+        if debugger.as_mut().is_some_and(|debugger| debugger.check_breakpoint(chip8)) {
+            break;
         }
     }
-    canvas.present();
+    *spent = spent.saturating_sub(budget);
+    if options.log_draws {
+        let stats: DrawStats = chip8.draw_stats();
+        if stats.sprites > 0 {
+            println!("Draws:\t\t{} sprites, {} pixels on, {} pixels off", stats.sprites, stats.pixels_on, stats.pixels_off);
+        }
+    }
+    if !paused {
+        chip8.tick_timers();
+    }
+    if !was_stopped && chip8.status() == Status::Idle {
+        info!(options.quiet, "Program finished (idle at 0x{:04X}).", chip8.pc());
+    }
+
+    Ok(())
+}
+
+/// Wraps an SDL error in an I/O error that says what was being attempted.
+fn context<E: Display>(action: &'static str) -> impl FnOnce(E) -> Error {
+    move |e| Error::other(format!("{}: {}", action, e))
+}
+
+/// Opens the default audio output with a sine tone queued up. With a gate, the tone plays continuously through an
+/// envelope that follows the gate; without one, it's paused until the sink is played.
+fn init_audio(gate: Option<Arc<AtomicBool>>) -> Result<(OutputStream, OutputStreamHandle, Sink), String> {
+    let (stream, stream_handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+    let sink: Sink = Sink::try_new(&stream_handle).map_err(|e| e.to_string())?;
+    let source = SineWave::new(SINE_FREQUENCY).repeat_infinite();
+    match gate {
+        Some(gate) => sink.append(Envelope::new(source, gate)),
+        None => {
+            sink.pause();
+            sink.append(source);
+        },
+    }
+    Ok((stream, stream_handle, sink))
 }
 
-/// Converts a keycode into a keypad index.
-fn process_key(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 =>    Some(0x1),
-        Keycode::Num2 =>    Some(0x2),
-        Keycode::Num3 =>    Some(0x3),
-        Keycode::Num4 =>    Some(0xC),
-        Keycode::Q =>       Some(0x4),
-        Keycode::W =>       Some(0x5),
-        Keycode::E =>       Some(0x6),
-        Keycode::R =>       Some(0xD),
-        Keycode::A =>       Some(0x7),
-        Keycode::S =>       Some(0x8),
-        Keycode::D =>       Some(0x9),
-        Keycode::F =>       Some(0xE),
-        Keycode::Z =>       Some(0xA),
-        Keycode::X =>       Some(0x0),
-        Keycode::C =>       Some(0xB),
-        Keycode::V =>       Some(0xF),
-        _ =>                None,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses options for a quiet headless run of a placeholder ROM, followed by the given options.
+    fn headless_options(extra: &[&str]) -> Options {
+        let args = ["rom.ch8", "--headless", "--quiet"].iter().chain(extra.iter()).map(|arg| arg.to_string());
+        Options::parse(args).unwrap()
+    }
+
+    #[test]
+    fn headless_runs_count_every_instruction() {
+        // Set two registers, then spin on a self-jump
+        let rom: &str = "6001 6102 1204";
+
+        // The self-jump that idles the program counts too
+        let mut chip8: Chip8 = Chip8::builder().detect_idle(true).build();
+        chip8.load_rom_hex(rom).unwrap();
+        assert_eq!(run_headless(&mut chip8, &headless_options(&[])).unwrap(), 3);
+        assert_eq!(chip8.status(), Status::Idle);
+
+        // The cycle limit is never overrun, whether it's hit before or exactly at the self-jump
+        for (limit, pc) in [(1, 0x202), (2, 0x204), (3, 0x204)] {
+            let mut chip8: Chip8 = Chip8::builder().detect_idle(true).build();
+            chip8.load_rom_hex(rom).unwrap();
+            let limit_arg: String = limit.to_string();
+            let executed: u64 = run_headless(&mut chip8, &headless_options(&["--run-cycles", &limit_arg])).unwrap();
+            assert_eq!(executed, limit);
+            assert_eq!(chip8.pc(), pc);
+        }
+    }
+    #[test]
+    fn headless_runs_stop_when_waiting_for_a_key() {
+        // Set V0, wait for a key into V1, then set V2
+        let mut chip8: Chip8 = Chip8::builder().detect_idle(true).build();
+        chip8.load_rom_hex("6001 F10A 6203").unwrap();
+        run_headless(&mut chip8, &headless_options(&[])).unwrap();
+        assert_eq!(chip8.status(), Status::Waiting);
+        assert_eq!(&chip8.reg_v()[0 .. 3], &[1, 0, 0]);
     }
 }
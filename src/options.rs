@@ -0,0 +1,435 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: options.rs                         //
+// Description: Command line parsing.       //
+// ---------------------------------------- //
+
+use crate::input::{InputMode, KeymapPreset};
+use crate::render::RenderMode;
+use chip8_rust::chip8::{AwaitKeyMode, MachineCodeBehavior};
+use chip8_rust::quirks::Quirks;
+use chip8_rust::timing::CostTable;
+
+#[cfg(feature = "quirks-file")]
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// Options remembered per ROM by `to_args`; saved files may hold others from older versions, which are ignored.
+#[cfg_attr(not(feature = "config"), allow(dead_code))]
+pub const PERSISTED_OPTIONS: [&str; 4] = ["--speed", "--draws-per-frame", "--quirks", "--quirk"];
+
+/// Usage string printed on bad arguments.
+pub const USAGE: &str = "Usage: chip8-rust [options] <ROM file path, or - for stdin>
+Options:
+  --render full|texture|dirty        Screen drawing strategy
+  --integer-scale                    Make the window resizable, scaling by whole numbers
+  --speed N                          Instructions per second
+  --adaptive-speed                   Lower the speed when frames run late
+  --cycle-costs                      Weight instructions by their cost
+  --cost X=N                         Cost of opcodes starting with hex digit X
+  --no-audio                         Run without sound
+  --debug-audio                      Click when the delay timer is set or runs out
+  --no-audio-fade                    Start and stop the buzzer abruptly
+  --sound-threshold N                Sound timer value above which the buzzer sounds
+  --input keycode|scancode           Match keys by character or position
+  --keymap-preset modern|numpad      Keyboard keys used for the keypad
+  --mouse-keypad                     Show a keypad that can be clicked or tapped
+  --await-key lowest|first           Key FX0A picks when several are held
+  --strict                           Treat undefined behavior as an error
+  --stack-depth N                    Maximum nested subroutine calls
+  --protect-font                     Block writes into the font region
+  --debug                            Control execution from a console prompt
+  --exit-on-idle                     Quit once the program spins on a self-jump
+  --headless                         Run without a window or sound until idle or waiting
+  --run-cycles N                     Quit after running N instructions
+  --skip-delay-loops                 Fast-forward delay timer waits (headless only)
+  --start-paused                     Wait for F8 or F9 before running
+  --screenshot-on-exit PATH          Save the final screen as a PNG
+  --dump-memory PATH                 Save the final memory as a binary file
+  --dump-regs PATH                   Save the final registers, timers and stack as text
+  --seed N                           Seed the random number generator
+  --dump-on-panic                    Print the machine state if the emulator crashes
+  --quiet                            Only print errors and requested output
+  --info                             Describe the ROM and quit without running it
+  --log-draws                        Print pixels drawn and erased per frame
+  --stats N                          Print frame timing every N frames
+  --coverage                         Print opcodes run on exit (profiling builds)
+  --draws-per-frame N                Limit sprite draws per frame
+  --quirk NAME=VALUE                 Set one quirk, e.g. shift=true
+  --quirks PATH                      Load quirks from a TOML file (quirks-file builds)
+  --machine-code error|skip|halt     0NNN handling
+  --extended-opcodes                 Run 5XY1 (CHIP-8E), 5XY2 and 5XY3 (XO-CHIP), FX75 and FX85 (SUPER-CHIP)
+  --persist-flags                    Keep FX75 flags between runs (config builds)";
+/// ROM path that means "read the ROM from stdin".
+pub const STDIN_ROM_PATH: &str = "-";
+/// Default number of instructions to run per second.
+const DEFAULT_SPEED: u32 = 600;
+
+/// Represents the options passed on the command line.
+pub struct Options {
+    /// Path to the ROM file to run.
+    pub rom_path: String,
+    /// Strategy used to draw the screen.
+    pub render_mode: RenderMode,
+    /// Whether the window is resizable, with the screen scaled by whole numbers.
+    pub integer_scale: bool,
+    /// Instructions to run per second.
+    pub speed: u32,
+    /// Whether to lower the speed when frames run late.
+    pub adaptive_speed: bool,
+    /// Instruction costs to spend the speed on, if not counting every instruction as 1.
+    pub cost_table: Option<CostTable>,
+    /// Whether to skip opening an audio device.
+    pub no_audio: bool,
+    /// Whether to click when the delay timer is set or runs out.
+    pub debug_audio: bool,
+    /// Whether to start and stop the buzzer without fading it in and out.
+    pub no_audio_fade: bool,
+    /// Sound timer value above which the buzzer sounds, if not the default.
+    pub sound_threshold: Option<u8>,
+    /// How keyboard keys are matched to the keypad.
+    pub input_mode: InputMode,
+    /// Which keyboard keys are used for the keypad.
+    pub keymap_preset: KeymapPreset,
+    /// Whether to show the keypad overlay and press its keys with the mouse.
+    pub mouse_keypad: bool,
+    /// Which key FX0A picks when several are held.
+    pub await_key_mode: AwaitKeyMode,
+    /// Whether undefined behavior raises errors.
+    pub strict: bool,
+    /// Maximum nested subroutine calls, if not the default.
+    pub stack_depth: Option<u8>,
+    /// Whether writes into the font region are blocked.
+    pub protect_font: bool,
+    /// Whether to run the console debugger.
+    pub debug: bool,
+    /// Whether to quit once the program spins on a self-jump.
+    pub exit_on_idle: bool,
+    /// Whether to run without a window, sound or input.
+    pub headless: bool,
+    /// Number of instructions to run before quitting.
+    pub run_cycles: Option<u64>,
+    /// Whether delay timer wait loops are fast-forwarded.
+    pub skip_delay_loops: bool,
+    /// Whether to wait for the resume or step key before running.
+    pub start_paused: bool,
+    /// Where to save the final screen on exit.
+    pub screenshot_path: Option<String>,
+    /// Where to save memory on exit, if anywhere.
+    pub dump_memory_path: Option<String>,
+    /// Where to save the registers, timers and stack on exit, if anywhere.
+    pub dump_regs_path: Option<String>,
+    /// Seed for the random number generator.
+    pub seed: Option<u64>,
+    /// Whether to print the machine state if the emulator panics.
+    pub dump_on_panic: bool,
+    /// Whether to suppress startup and status output.
+    pub quiet: bool,
+    /// Whether to describe the ROM and quit without running it.
+    pub info: bool,
+    /// Whether to print draw counters every frame.
+    pub log_draws: bool,
+    /// Frames between frame timing reports, if any.
+    pub stats: Option<u32>,
+    /// Whether to print opcode coverage on exit.
+    #[cfg(feature = "profiling")]
+    pub coverage: bool,
+    /// Compatibility quirks to run with.
+    pub quirks: Quirks,
+    /// The quirks before any set on the command line: the quirks file's, or the defaults.
+    pub base_quirks: Quirks,
+    /// The TOML file the quirks were loaded from, if any.
+    #[cfg(feature = "quirks-file")]
+    pub quirks_path: Option<String>,
+    /// How 0NNN is handled, if overridden.
+    pub machine_code: Option<MachineCodeBehavior>,
+    /// Whether the extended 5XY1-5XY3, FX75 and FX85 opcodes are decoded.
+    pub extended_opcodes: bool,
+    /// Whether to load and save the RPL flags in the config directory.
+    #[cfg(feature = "config")]
+    pub persist_flags: bool,
+}
+
+impl Options {
+    /// Attempts to parse options from command line arguments (excluding the program name).
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, Error> {
+        let mut rom_path: Option<String> = None;
+        let mut render_mode: RenderMode = RenderMode::Full;
+        let mut integer_scale: bool = false;
+        let mut speed: u32 = DEFAULT_SPEED;
+        let mut adaptive_speed: bool = false;
+        let mut cost_table: Option<CostTable> = None;
+        let mut no_audio: bool = false;
+        let mut debug_audio: bool = false;
+        let mut no_audio_fade: bool = false;
+        let mut sound_threshold: Option<u8> = None;
+        let mut input_mode: InputMode = InputMode::Keycode;
+        let mut keymap_preset: KeymapPreset = KeymapPreset::Modern;
+        let mut mouse_keypad: bool = false;
+        let mut await_key_mode: AwaitKeyMode = AwaitKeyMode::LowestIndex;
+        let mut strict: bool = false;
+        let mut stack_depth: Option<u8> = None;
+        let mut protect_font: bool = false;
+        let mut debug: bool = false;
+        let mut exit_on_idle: bool = false;
+        let mut headless: bool = false;
+        let mut run_cycles: Option<u64> = None;
+        let mut skip_delay_loops: bool = false;
+        let mut start_paused: bool = false;
+        let mut screenshot_path: Option<String> = None;
+        let mut dump_memory_path: Option<String> = None;
+        let mut dump_regs_path: Option<String> = None;
+        let mut seed: Option<u64> = None;
+        let mut dump_on_panic: bool = false;
+        let mut quiet: bool = false;
+        let mut info: bool = false;
+        let mut log_draws: bool = false;
+        let mut stats: Option<u32> = None;
+        #[cfg(feature = "profiling")]
+        let mut coverage: bool = false;
+        let mut quirks: Quirks = Quirks::default();
+        let mut quirk_settings: Vec<(String, String)> = Vec::new();
+        #[cfg(feature = "quirks-file")]
+        let mut quirks_path: Option<String> = None;
+        let mut machine_code: Option<MachineCodeBehavior> = None;
+        let mut extended_opcodes: bool = false;
+        #[cfg(feature = "config")]
+        let mut persist_flags: bool = false;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--render" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    render_mode = match value.as_str() {
+                        "full" => RenderMode::Full,
+                        "texture" => RenderMode::Texture,
+                        "dirty" => RenderMode::Dirty,
+                        _ => return Err(invalid(format!("Unknown render mode '{}'.", value))),
+                    };
+                },
+                "--integer-scale" => integer_scale = true,
+                "--speed" => speed = parse_value(&mut args, &arg)?,
+                "--adaptive-speed" => adaptive_speed = true,
+                "--cycle-costs" => {
+                    cost_table.get_or_insert_with(CostTable::default);
+                },
+                "--cost" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    let (group, cost) = value.split_once('=')
+                        .filter(|(group, _)| group.len() == 1)
+                        .and_then(|(group, cost)| Some((group.chars().next()?.to_digit(16)?, cost.parse::<u32>().ok()?)))
+                        .filter(|(_, cost)| *cost > 0)
+                        .ok_or_else(|| invalid(format!("Invalid value '{}' for '{}'.", value, arg)))?;
+                    cost_table.get_or_insert_with(CostTable::default).costs[group as usize] = cost;
+                },
+                "--no-audio" => no_audio = true,
+                "--debug-audio" => debug_audio = true,
+                "--no-audio-fade" => no_audio_fade = true,
+                "--sound-threshold" => sound_threshold = Some(parse_value(&mut args, &arg)?),
+                "--input" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    input_mode = match value.as_str() {
+                        "keycode" => InputMode::Keycode,
+                        "scancode" => InputMode::Scancode,
+                        _ => return Err(invalid(format!("Unknown input mode '{}'.", value))),
+                    };
+                },
+                "--keymap-preset" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    keymap_preset = match value.as_str() {
+                        "modern" => KeymapPreset::Modern,
+                        "numpad" => KeymapPreset::Numpad,
+                        _ => return Err(invalid(format!("Unknown keymap preset '{}'.", value))),
+                    };
+                },
+                "--mouse-keypad" => mouse_keypad = true,
+                "--await-key" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    await_key_mode = match value.as_str() {
+                        "lowest" => AwaitKeyMode::LowestIndex,
+                        "first" => AwaitKeyMode::FirstPress,
+                        _ => return Err(invalid(format!("Unknown await key mode '{}'.", value))),
+                    };
+                },
+                "--strict" => strict = true,
+                "--stack-depth" => stack_depth = Some(parse_value(&mut args, &arg)?),
+                "--protect-font" => protect_font = true,
+                "--debug" => debug = true,
+                "--exit-on-idle" => exit_on_idle = true,
+                "--headless" => headless = true,
+                "--run-cycles" => run_cycles = Some(parse_value(&mut args, &arg)?),
+                "--skip-delay-loops" => skip_delay_loops = true,
+                "--start-paused" => start_paused = true,
+                "--screenshot-on-exit" => screenshot_path = Some(next_value(&mut args, &arg)?),
+                "--dump-memory" => dump_memory_path = Some(next_value(&mut args, &arg)?),
+                "--dump-regs" => dump_regs_path = Some(next_value(&mut args, &arg)?),
+                "--seed" => seed = Some(parse_value(&mut args, &arg)?),
+                "--dump-on-panic" => dump_on_panic = true,
+                "--quiet" => quiet = true,
+                "--info" => info = true,
+                "--log-draws" => log_draws = true,
+                "--stats" => stats = Some(parse_value(&mut args, &arg)?),
+                #[cfg(feature = "profiling")]
+                "--coverage" => coverage = true,
+                "--draws-per-frame" => {
+                    let limit: u32 = parse_value(&mut args, &arg)?;
+                    quirk_settings.push(("draws_per_frame".to_string(), limit.to_string()));
+                },
+                "--quirk" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    let (name, setting) = value.split_once('=')
+                        .ok_or_else(|| invalid(format!("Invalid value '{}' for '{}'.", value, arg)))?;
+                    quirk_settings.push((name.to_string(), setting.to_string()));
+                },
+                #[cfg(feature = "quirks-file")]
+                "--quirks" => quirks_path = Some(next_value(&mut args, &arg)?),
+                "--machine-code" => {
+                    let value: String = next_value(&mut args, &arg)?;
+                    machine_code = Some(match value.as_str() {
+                        "error" => MachineCodeBehavior::Error,
+                        "skip" => MachineCodeBehavior::Skip,
+                        "halt" => MachineCodeBehavior::Halt,
+                        _ => return Err(invalid(format!("Unknown machine code behavior '{}'.", value))),
+                    });
+                },
+                "--extended-opcodes" => extended_opcodes = true,
+                #[cfg(feature = "config")]
+                "--persist-flags" => persist_flags = true,
+                _ if arg.starts_with("--") => return Err(invalid(format!("Unknown option '{}'.", arg))),
+                _ if rom_path.is_none() => rom_path = Some(arg),
+                _ => return Err(invalid(format!("Unexpected argument '{}'.", arg))),
+            }
+        }
+
+        let rom_path: String = rom_path.ok_or_else(|| invalid("No ROM file provided.".to_string()))?;
+
+        // Start from the quirks file, then apply quirks given as options in order, so later ones win
+        #[cfg(feature = "quirks-file")]
+        if let Some(path) = quirks_path.as_mut() {
+            let text: String = fs::read_to_string(&*path)
+                .map_err(|e| Error::new(e.kind(), format!("Failed to read quirks file '{}': {}", path, e)))?;
+            quirks = Quirks::from_toml(&text)
+                .map_err(|message| Error::new(ErrorKind::InvalidData, format!("Invalid quirks file '{}': {}", path, message)))?;
+            // Remember the full path, so saved settings still find the file from another directory
+            if let Some(full_path) = fs::canonicalize(&*path).ok().and_then(|full_path| full_path.to_str().map(str::to_string)) {
+                *path = full_path;
+            }
+        }
+        let base_quirks: Quirks = quirks;
+        for (name, value) in quirk_settings.iter() {
+            quirks.set(name, value).map_err(invalid)?;
+        }
+        if rom_path == STDIN_ROM_PATH && debug {
+            return Err(invalid("The debugger reads commands from stdin, so it can't be used with a ROM from stdin.".to_string()));
+        }
+        if headless && start_paused {
+            return Err(invalid("--start-paused waits for a key, so it can't be used with --headless.".to_string()));
+        }
+        if debug_audio && (no_audio || headless) {
+            return Err(invalid("--debug-audio plays through the audio device, so it can't be used with --no-audio or --headless.".to_string()));
+        }
+        #[cfg(feature = "config")]
+        if persist_flags && !extended_opcodes {
+            return Err(invalid("--persist-flags keeps what FX75 saves, so it needs --extended-opcodes.".to_string()));
+        }
+        if skip_delay_loops && !headless {
+            return Err(invalid("--skip-delay-loops makes time pass faster than real time, so it needs --headless.".to_string()));
+        }
+        if stats == Some(0) {
+            return Err(invalid("--stats needs at least 1 frame between reports.".to_string()));
+        }
+        if headless && debug {
+            return Err(invalid("The debugger only works in windowed mode, so it can't be used with --headless.".to_string()));
+        }
+
+        Ok(Options {
+            rom_path,
+            render_mode,
+            integer_scale,
+            speed,
+            adaptive_speed,
+            cost_table,
+            no_audio,
+            debug_audio,
+            no_audio_fade,
+            sound_threshold,
+            input_mode,
+            keymap_preset,
+            mouse_keypad,
+            await_key_mode,
+            strict,
+            stack_depth,
+            protect_font,
+            debug,
+            exit_on_idle,
+            headless,
+            run_cycles,
+            skip_delay_loops,
+            start_paused,
+            screenshot_path,
+            dump_memory_path,
+            dump_regs_path,
+            seed,
+            dump_on_panic,
+            quiet,
+            info,
+            log_draws,
+            stats,
+            #[cfg(feature = "profiling")]
+            coverage,
+            quirks,
+            base_quirks,
+            #[cfg(feature = "quirks-file")]
+            quirks_path,
+            machine_code,
+            extended_opcodes,
+            #[cfg(feature = "config")]
+            persist_flags,
+        })
+    }
+}
+
+impl Options {
+    /// Converts the settings remembered per ROM back into command line arguments, one argument per element so
+    /// values containing spaces survive a round trip. These are the speed, the quirks file and each quirk in
+    /// `quirks` that differs from the file (or the defaults); pass the interpreter's quirks so changes made while
+    /// running are kept too.
+    #[cfg_attr(not(feature = "config"), allow(dead_code))]
+    pub fn to_args(&self, quirks: &Quirks) -> Vec<String> {
+        let mut args: Vec<String> = vec!["--speed".to_string(), self.speed.to_string()];
+        #[cfg(feature = "quirks-file")]
+        if let Some(path) = &self.quirks_path {
+            args.push("--quirks".to_string());
+            args.push(path.clone());
+        }
+        let changed: Vec<&str> = self.base_quirks.diff(quirks);
+        for (name, value) in quirks.values() {
+            if changed.contains(&name) {
+                args.push("--quirk".to_string());
+                args.push(format!("{}={}", name, value));
+            }
+        }
+        args
+    }
+}
+
+/// Gets the value following an option, failing if there isn't one.
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, Error> {
+    args.next().ok_or_else(|| invalid(format!("Missing value for '{}'.", option)))
+}
+
+/// Parses the value following an option, failing if it's missing or malformed.
+fn parse_value<T: FromStr>(args: &mut impl Iterator<Item = String>, option: &str) -> Result<T, Error> {
+    let value: String = next_value(args, option)?;
+    value.parse().map_err(|_| invalid(format!("Invalid value '{}' for '{}'.", value, option)))
+}
+
+/// Builds an invalid argument error that includes the usage string.
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("{}\n{}", message, USAGE))
+}
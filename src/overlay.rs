@@ -0,0 +1,137 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: overlay.rs                         //
+// Description: Debug overlays drawn over   //
+//              the emulated screen.        //
+// ---------------------------------------- //
+
+use crate::render::SCALE_FACTOR;
+use chip8_rust::chip8::*;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+/// Spacing between grid lines in CHIP-8 pixels.
+const GRID_SPACING: u8 = 8;
+/// The color of grid lines.
+const COLOR_GRID: Color = Color::RGBA(0xFF, 0xFF, 0xFF, 0x30);
+/// The color of grid labels.
+const COLOR_LABEL: Color = Color::RGBA(0xFF, 0xFF, 0xFF, 0xA0);
+/// Size of a label font pixel in window pixels.
+const LABEL_SCALE: u32 = 2;
+/// Keypad keys as laid out on the COSMAC VIP, row by row.
+const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+/// Size of a keypad key in window pixels.
+const KEY_SIZE: u32 = 18;
+/// Space between keypad keys, and between the keypad and the window edge, in window pixels.
+const KEY_GAP: u32 = 2;
+/// The color of released keys.
+const COLOR_KEY_UP: Color = Color::RGBA(0x00, 0x00, 0x00, 0x80);
+/// The color of pressed keys.
+const COLOR_KEY_DOWN: Color = Color::RGBA(0xFF, 0xFF, 0xFF, 0xC0);
+
+/// Draws faint gridlines every few CHIP-8 pixels, labelled with their coordinates in hex.
+pub fn draw_grid(canvas: &mut Canvas<Window>) -> Result<(), String> {
+    canvas.set_blend_mode(BlendMode::Blend);
+
+    // Vertical lines, labelled along the top
+    for x in (GRID_SPACING .. SCREEN_WIDTH).step_by(GRID_SPACING as usize) {
+        let window_x: i32 = (x as u32 * SCALE_FACTOR) as i32;
+        canvas.set_draw_color(COLOR_GRID);
+        canvas.fill_rect(Rect::new(window_x, 0, 1, SCREEN_HEIGHT as u32 * SCALE_FACTOR))?;
+        draw_hex_label(canvas, x, window_x + 2, 2)?;
+    }
+
+    // Horizontal lines, labelled along the left
+    for y in (GRID_SPACING .. SCREEN_HEIGHT).step_by(GRID_SPACING as usize) {
+        let window_y: i32 = (y as u32 * SCALE_FACTOR) as i32;
+        canvas.set_draw_color(COLOR_GRID);
+        canvas.fill_rect(Rect::new(0, window_y, SCREEN_WIDTH as u32 * SCALE_FACTOR, 1))?;
+        draw_hex_label(canvas, y, 2, window_y + 2)?;
+    }
+
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// Draws the 4x4 hex keypad in the bottom right corner, highlighting the keys that are held.
+pub fn draw_keypad(canvas: &mut Canvas<Window>, keypad: &[bool; 16]) -> Result<(), String> {
+    canvas.set_blend_mode(BlendMode::Blend);
+
+    for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+        for (col, key) in keys.iter().enumerate() {
+            let rect: Rect = key_rect(row, col);
+            let pressed: bool = keypad[*key as usize];
+            canvas.set_draw_color(if pressed { COLOR_KEY_DOWN } else { COLOR_KEY_UP });
+            canvas.fill_rect(rect)?;
+
+            // Center the 4x5 glyph on the key
+            canvas.set_draw_color(if pressed { COLOR_KEY_UP } else { COLOR_LABEL });
+            let glyph_x: i32 = rect.x() + ((KEY_SIZE - 4 * LABEL_SCALE) / 2) as i32;
+            let glyph_y: i32 = rect.y() + ((KEY_SIZE - 5 * LABEL_SCALE) / 2) as i32;
+            draw_hex_digit(canvas, *key, glyph_x, glyph_y)?;
+        }
+    }
+
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// Finds the keypad key drawn by `draw_keypad` under a window position, if any.
+pub fn keypad_key_at(x: i32, y: i32) -> Option<u8> {
+    for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+        for (col, key) in keys.iter().enumerate() {
+            if key_rect(row, col).contains_point((x, y)) {
+                return Some(*key);
+            }
+        }
+    }
+    None
+}
+
+/// Gets the window area of the keypad key at a row and column of the layout.
+fn key_rect(row: usize, col: usize) -> Rect {
+    let pad_size: u32 = 4 * KEY_SIZE + 3 * KEY_GAP;
+    let left: i32 = (SCREEN_WIDTH as u32 * SCALE_FACTOR - pad_size - KEY_GAP) as i32;
+    let top: i32 = (SCREEN_HEIGHT as u32 * SCALE_FACTOR - pad_size - KEY_GAP) as i32;
+    let key_x: i32 = left + (col as u32 * (KEY_SIZE + KEY_GAP)) as i32;
+    let key_y: i32 = top + (row as u32 * (KEY_SIZE + KEY_GAP)) as i32;
+    Rect::new(key_x, key_y, KEY_SIZE, KEY_SIZE)
+}
+
+/// Draws a two-digit hex number using the CHIP-8 font at a window position.
+fn draw_hex_label(canvas: &mut Canvas<Window>, value: u8, x: i32, y: i32) -> Result<(), String> {
+    canvas.set_draw_color(COLOR_LABEL);
+    for (digit_index, digit) in [value >> 4, value & 0xF].iter().enumerate() {
+        draw_hex_digit(canvas, *digit, x + (digit_index as u32 * 5 * LABEL_SCALE) as i32, y)?;
+    }
+    Ok(())
+}
+
+/// Draws one hex digit using the CHIP-8 font at a window position, in the current draw color.
+fn draw_hex_digit(canvas: &mut Canvas<Window>, digit: u8, x: i32, y: i32) -> Result<(), String> {
+    let glyph: &[u8] = &FONT_DATA[digit as usize * 5 .. digit as usize * 5 + 5];
+    for (row, bits) in glyph.iter().enumerate() {
+        for col in 0 .. 4 {
+            if bits & (0x80 >> col) != 0 {
+                canvas.fill_rect(Rect::new(
+                    x + (col * LABEL_SCALE) as i32,
+                    y + (row as u32 * LABEL_SCALE) as i32,
+                    LABEL_SCALE,
+                    LABEL_SCALE,
+                ))?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: quirks.rs                          //
+// Description: Interpreter compatibility   //
+//              settings.                   //
+// ---------------------------------------- //
+
+/// Represents behaviors that differ between CHIP-8 interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// 8XY6 and 8XYE shift VX in place instead of shifting VY into VX.
+    pub shift: bool,
+    /// FX55 leaves I pointing past the last register stored.
+    pub store_increment: bool,
+    /// FX65 leaves I pointing past the last register loaded.
+    pub load_increment: bool,
+    /// FX1E sets VF to 1 when I goes past 0x0FFF, and to 0 otherwise (Amiga interpreter; Spacefight 2091 needs it).
+    pub index_overflow_flag: bool,
+    /// DXYN sets VF to the number of sprite rows that collided or were clipped off the bottom (SCHIP/XO-CHIP),
+    /// instead of 1 for any collision.
+    pub vf_row_count: bool,
+    /// DXYN wraps sprites past the right edge around to the left instead of clipping them.
+    pub wrap_x: bool,
+    /// DXYN wraps sprites past the bottom edge around to the top instead of clipping them.
+    pub wrap_y: bool,
+    /// DXYN draws nothing when VX or VY is past the edge of the screen, instead of wrapping the start position
+    /// back onto it. This only concerns where a sprite starts; `wrap_x` and `wrap_y` cover sprites crossing an edge.
+    pub clip_start: bool,
+    /// Maximum number of DXYN draws per frame; further draws wait for the next frame (Octo's vblank behavior).
+    pub draws_per_frame: Option<u32>,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            vf_reset: true,
+            shift: false,
+            store_increment: true,
+            load_increment: true,
+            index_overflow_flag: false,
+            vf_row_count: false,
+            wrap_x: false,
+            wrap_y: false,
+            clip_start: false,
+            draws_per_frame: None,
+        }
+    }
+}
+
+impl Quirks {
+    /// Lists the names of the quirks that are set differently in `other`.
+    pub fn diff(&self, other: &Quirks) -> Vec<&'static str> {
+        self.values().into_iter()
+            .zip(other.values())
+            .filter(|((_, value), (_, other_value))| value != other_value)
+            .map(|((name, _), _)| name)
+            .collect()
+    }
+
+    /// Lists every quirk by field name with its value as text, in the form `set` accepts: `true` or `false`, and
+    /// a number or `none` for `draws_per_frame`.
+    pub fn values(&self) -> [(&'static str, String); 10] {
+        [
+            ("vf_reset", self.vf_reset.to_string()),
+            ("shift", self.shift.to_string()),
+            ("store_increment", self.store_increment.to_string()),
+            ("load_increment", self.load_increment.to_string()),
+            ("index_overflow_flag", self.index_overflow_flag.to_string()),
+            ("vf_row_count", self.vf_row_count.to_string()),
+            ("wrap_x", self.wrap_x.to_string()),
+            ("wrap_y", self.wrap_y.to_string()),
+            ("clip_start", self.clip_start.to_string()),
+            ("draws_per_frame", self.draws_per_frame.map_or("none".to_string(), |limit| limit.to_string())),
+        ]
+    }
+
+    /// Attempts to set one quirk by field name from text, as listed by `values`. Fails on unknown names and on
+    /// values that don't fit the quirk, naming the offending one.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let flag = || value.parse::<bool>().map_err(|_| format!("Quirk '{}' must be true or false.", name));
+        match name {
+            "vf_reset" => self.vf_reset = flag()?,
+            "shift" => self.shift = flag()?,
+            "store_increment" => self.store_increment = flag()?,
+            "load_increment" => self.load_increment = flag()?,
+            "index_overflow_flag" => self.index_overflow_flag = flag()?,
+            "vf_row_count" => self.vf_row_count = flag()?,
+            "wrap_x" => self.wrap_x = flag()?,
+            "wrap_y" => self.wrap_y = flag()?,
+            "clip_start" => self.clip_start = flag()?,
+            "draws_per_frame" if value == "none" => self.draws_per_frame = None,
+            "draws_per_frame" => {
+                let limit: u32 = value.parse()
+                    .ok()
+                    .filter(|limit| *limit > 0)
+                    .ok_or_else(|| format!("Quirk '{}' must be a positive whole number or none.", name))?;
+                self.draws_per_frame = Some(limit);
+            },
+            _ => return Err(format!("Unknown quirk '{}'.", name)),
+        }
+        Ok(())
+    }
+
+    /// Attempts to parse quirks from TOML, with the same names as the fields (e.g. `shift = true`). Quirks left
+    /// out keep their defaults. Fails on unknown names and on values of the wrong type, naming the offending key.
+    #[cfg(feature = "quirks-file")]
+    pub fn from_toml(text: &str) -> Result<Quirks, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut quirks: Quirks = Quirks::default();
+        for (key, value) in table.iter() {
+            let flag = || value.as_bool().ok_or_else(|| format!("Quirk '{}' must be true or false.", key));
+            match key.as_str() {
+                "vf_reset" => quirks.vf_reset = flag()?,
+                "shift" => quirks.shift = flag()?,
+                "store_increment" => quirks.store_increment = flag()?,
+                "load_increment" => quirks.load_increment = flag()?,
+                "index_overflow_flag" => quirks.index_overflow_flag = flag()?,
+                "vf_row_count" => quirks.vf_row_count = flag()?,
+                "wrap_x" => quirks.wrap_x = flag()?,
+                "wrap_y" => quirks.wrap_y = flag()?,
+                "clip_start" => quirks.clip_start = flag()?,
+                "draws_per_frame" => {
+                    let limit: u32 = value.as_integer()
+                        .and_then(|limit| u32::try_from(limit).ok())
+                        .filter(|limit| *limit > 0)
+                        .ok_or_else(|| format!("Quirk '{}' must be a positive whole number.", key))?;
+                    quirks.draws_per_frame = Some(limit);
+                },
+                _ => return Err(format!("Unknown quirk '{}'.", key)),
+            }
+        }
+        Ok(quirks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_names_changed_quirks() {
+        let plain: Quirks = Quirks::default();
+        assert!(plain.diff(&plain).is_empty());
+
+        let changed: Quirks = Quirks { shift: true, draws_per_frame: Some(1), ..Quirks::default() };
+        assert_eq!(plain.diff(&changed), vec!["shift", "draws_per_frame"]);
+        assert_eq!(changed.diff(&plain), vec!["shift", "draws_per_frame"]);
+    }
+
+    #[test]
+    fn set_reads_back_every_value() {
+        let changed: Quirks = Quirks {
+            vf_reset: false,
+            shift: true,
+            wrap_y: true,
+            clip_start: true,
+            draws_per_frame: Some(3),
+            ..Quirks::default()
+        };
+        let mut quirks: Quirks = Quirks::default();
+        for (name, value) in changed.values() {
+            quirks.set(name, &value).unwrap();
+        }
+        assert_eq!(quirks, changed);
+        quirks.set("draws_per_frame", "none").unwrap();
+        assert_eq!(quirks.draws_per_frame, None);
+
+        assert_eq!(quirks.set("shfit", "true"), Err("Unknown quirk 'shfit'.".to_string()));
+        assert_eq!(quirks.set("shift", "1"), Err("Quirk 'shift' must be true or false.".to_string()));
+        assert!(quirks.set("draws_per_frame", "0").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "quirks-file")]
+    fn from_toml_fills_in_defaults_and_rejects_unknown_keys() {
+        let quirks: Quirks = Quirks::from_toml("shift = true\nvf_reset = false\ndraws_per_frame = 1\n").unwrap();
+        assert_eq!(quirks, Quirks { shift: true, vf_reset: false, draws_per_frame: Some(1), ..Quirks::default() });
+        assert_eq!(Quirks::from_toml(""), Ok(Quirks::default()));
+
+        assert_eq!(Quirks::from_toml("shfit = true"), Err("Unknown quirk 'shfit'.".to_string()));
+        assert_eq!(Quirks::from_toml("shift = 1"), Err("Quirk 'shift' must be true or false.".to_string()));
+        assert!(Quirks::from_toml("draws_per_frame = 0").is_err());
+        assert!(Quirks::from_toml("shift = ").is_err());
+    }
+}
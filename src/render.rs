@@ -0,0 +1,178 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: render.rs                          //
+// Description: Screen drawing strategies.  //
+// ---------------------------------------- //
+
+use chip8_rust::chip8::*;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+/// Factor by which to scale the window up.
+pub const SCALE_FACTOR: u32 = 8;
+/// The color of "off" pixels.
+pub const COLOR_OFF: Color = Color::RGB(0x66, 0x10, 0x4B);
+/// The color of "on" pixels.
+pub const COLOR_ON: Color = Color::RGB(0xDB, 0x22, 0xA1);
+
+/// Represents a strategy for drawing the screen.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Clears the canvas and fills a rect per "on" pixel every frame.
+    Full,
+    /// Uploads the whole buffer to a streaming texture every frame.
+    Texture,
+    /// Redraws only pixels that changed since last frame into a target texture.
+    Dirty,
+}
+
+/// Draws the CHIP-8 screen onto a canvas using the selected strategy.
+pub struct Renderer<'a> {
+    /// The selected strategy.
+    mode: RenderMode,
+    /// Creates the screen texture, again whenever the resolution changes.
+    texture_creator: &'a TextureCreator<WindowContext>,
+    /// Screen-sized texture used by the texture and dirty strategies.
+    texture: Option<Texture<'a>>,
+    /// The resolution the texture and previous buffer were made for.
+    size: (usize, usize),
+    /// The buffer as of the last draw, used by the dirty strategy.
+    previous_buffer: Vec<bool>,
+    /// Whether the next draw must update the whole texture.
+    full_redraw: bool,
+}
+
+impl<'a> Renderer<'a> {
+    /// Initializes a renderer, creating any texture the strategy needs.
+    pub fn new(mode: RenderMode, texture_creator: &'a TextureCreator<WindowContext>) -> Result<Renderer<'a>, String> {
+        let mut renderer: Renderer<'a> = Renderer {
+            mode,
+            texture_creator,
+            texture: None,
+            size: (0, 0),
+            previous_buffer: Vec::new(),
+            full_redraw: true,
+        };
+        renderer.resize(SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize)?;
+        Ok(renderer)
+    }
+
+    /// Recreates the texture and previous buffer for a new resolution, forcing a full redraw.
+    fn resize(&mut self, width: usize, height: usize) -> Result<(), String> {
+        self.texture = match self.mode {
+            RenderMode::Full => None,
+            RenderMode::Texture => Some(
+                self.texture_creator
+                    .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+                    .map_err(|e| e.to_string())?,
+            ),
+            RenderMode::Dirty => Some(
+                self.texture_creator
+                    .create_texture_target(PixelFormatEnum::RGBA8888, width as u32, height as u32)
+                    .map_err(|e| e.to_string())?,
+            ),
+        };
+        self.size = (width, height);
+        self.previous_buffer = vec![false; width * height];
+        self.full_redraw = true;
+        Ok(())
+    }
+
+    /// Draws the screen onto the canvas, leaving presenting to the caller so overlays can be added.
+    /// When `changed` is false, the texture strategies reuse their texture instead of updating it.
+    ///
+    /// Call this once per frame after running it, with `changed` from `Chip8::take_display_dirty`. The screen then
+    /// shows the buffer as it stands at the end of the frame, however many sprites were drawn during it.
+    pub fn draw(&mut self, chip8: &Chip8, canvas: &mut Canvas<Window>, changed: bool) -> Result<(), String> {
+        if self.size != (chip8.width(), chip8.height()) {
+            self.resize(chip8.width(), chip8.height())?;
+        }
+        match self.mode {
+            RenderMode::Full => self.draw_full(chip8, canvas),
+            RenderMode::Texture => self.draw_texture(chip8, canvas, changed),
+            RenderMode::Dirty => self.draw_dirty(chip8, canvas, changed),
+        }
+    }
+
+    /// Clears the canvas and draws every "on" pixel as a rect.
+    fn draw_full(&mut self, chip8: &Chip8, canvas: &mut Canvas<Window>) -> Result<(), String> {
+        // Clear canvas
+        canvas.set_draw_color(COLOR_OFF);
+        canvas.clear();
+
+        // Draw in rects as pixels, scaled so any resolution fills the window, in a single call
+        let width: usize = chip8.width();
+        let scale: u32 = SCALE_FACTOR * SCREEN_WIDTH as u32 / width as u32;
+        let rects: Vec<Rect> = chip8.framebuffer().iter().enumerate()
+            .filter(|(_, pixel)| **pixel)
+            .map(|(i, _)| {
+                let x = (i % width) as u32;
+                let y = (i / width) as u32;
+                Rect::new((x * scale) as i32, (y * scale) as i32, scale, scale)
+            })
+            .collect();
+        canvas.set_draw_color(COLOR_ON);
+        canvas.fill_rects(&rects)
+    }
+
+    /// Writes every pixel into a streaming texture and stretches it over the canvas.
+    fn draw_texture(&mut self, chip8: &Chip8, canvas: &mut Canvas<Window>, changed: bool) -> Result<(), String> {
+        let texture: &mut Texture = self.texture.as_mut().ok_or("Missing screen texture.")?;
+        if !changed && !self.full_redraw {
+            return canvas.copy(texture, None, None);
+        }
+        let width: usize = chip8.width();
+        texture.with_lock(None, |bytes: &mut [u8], pitch: usize| {
+            for (i, pixel) in chip8.framebuffer().iter().enumerate() {
+                let x = i % width;
+                let y = i / width;
+                let color: Color = if *pixel { COLOR_ON } else { COLOR_OFF };
+                let offset: usize = y * pitch + x * 3;
+                bytes[offset] = color.r;
+                bytes[offset + 1] = color.g;
+                bytes[offset + 2] = color.b;
+            }
+        })?;
+        self.full_redraw = false;
+        canvas.copy(texture, None, None)
+    }
+
+    /// Redraws changed pixels into a target texture and stretches it over the canvas.
+    fn draw_dirty(&mut self, chip8: &Chip8, canvas: &mut Canvas<Window>, changed: bool) -> Result<(), String> {
+        let texture: &mut Texture = self.texture.as_mut().ok_or("Missing screen texture.")?;
+        if !changed && !self.full_redraw {
+            return canvas.copy(texture, None, None);
+        }
+        let previous_buffer: &mut [bool] = &mut self.previous_buffer;
+        let full_redraw: bool = self.full_redraw;
+        let width: usize = chip8.width();
+        let mut draw_result: Result<(), String> = Ok(());
+
+        canvas
+            .with_texture_canvas(texture, |texture_canvas| {
+                for (i, pixel) in chip8.framebuffer().iter().enumerate() {
+                    if full_redraw || previous_buffer[i] != *pixel {
+                        let x = (i % width) as i32;
+                        let y = (i / width) as i32;
+                        texture_canvas.set_draw_color(if *pixel { COLOR_ON } else { COLOR_OFF });
+                        if let Err(e) = texture_canvas.draw_point((x, y)) {
+                            draw_result = Err(e);
+                            return;
+                        }
+                        previous_buffer[i] = *pixel;
+                    }
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        draw_result?;
+        self.full_redraw = false;
+
+        canvas.copy(texture, None, None)
+    }
+}
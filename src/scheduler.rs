@@ -0,0 +1,99 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: scheduler.rs                       //
+// Description: Per-frame instruction       //
+//              budgeting.                  //
+// ---------------------------------------- //
+
+use std::time::{Duration, Instant};
+
+/// How late a frame may run (relative to the refresh interval) before throttling.
+const THROTTLE_SLACK: f64 = 1.25;
+/// How quickly the budget shrinks when behind, as a fraction of the current budget.
+const THROTTLE_STEP: f64 = 0.9;
+/// How quickly the budget recovers when on time, as a fraction of the target budget.
+const RECOVERY_STEP: usize = 20;
+/// The most instructions per frame the speed keys can ask for.
+const MAX_TICKS_PER_FRAME: usize = 1000;
+
+/// Decides how many instructions to run each frame, optionally adapting to keep up with the display.
+pub struct Scheduler {
+    /// The desired instructions per frame.
+    target_ticks: usize,
+    /// The current instructions per frame.
+    ticks: usize,
+    /// The display refresh rate in Hz.
+    refresh_rate: u32,
+    /// Whether to adapt the budget to frame times.
+    adaptive: bool,
+    /// Whether to keep budget changes to itself.
+    quiet: bool,
+    /// When the previous frame ended.
+    last_frame: Instant,
+}
+
+impl Scheduler {
+    /// Initializes a scheduler running `speed` instructions per second at the given refresh rate.
+    pub fn new(speed: u32, refresh_rate: u32, adaptive: bool, quiet: bool) -> Scheduler {
+        let target_ticks: usize = (speed / refresh_rate.max(1)).max(1) as usize;
+        Scheduler {
+            target_ticks,
+            ticks: target_ticks,
+            refresh_rate: refresh_rate.max(1),
+            adaptive,
+            quiet,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// Gets the number of instructions to run this frame.
+    pub fn ticks_per_frame(&self) -> usize {
+        self.ticks
+    }
+
+    /// Gets the instructions per second currently being achieved.
+    pub fn effective_ips(&self) -> u32 {
+        self.ticks as u32 * self.refresh_rate
+    }
+
+    /// Changes the desired instructions per frame by `step`, keeping it between 1 and `MAX_TICKS_PER_FRAME`, and
+    /// returns the new value. Any throttling starts over from the new target.
+    pub fn adjust_ticks(&mut self, step: isize) -> usize {
+        self.target_ticks = self.target_ticks.saturating_add_signed(step).clamp(1, MAX_TICKS_PER_FRAME);
+        self.ticks = self.target_ticks;
+        self.ticks
+    }
+
+    /// Records the end of a frame, adjusting the budget if adaptive. Returns true if the budget changed.
+    pub fn end_frame(&mut self) -> bool {
+        let now: Instant = Instant::now();
+        let elapsed: Duration = now - self.last_frame;
+        self.last_frame = now;
+
+        if !self.adaptive {
+            return false;
+        }
+
+        let frame_time: f64 = 1.0 / self.refresh_rate as f64;
+        let previous_ticks: usize = self.ticks;
+        if elapsed.as_secs_f64() > frame_time * THROTTLE_SLACK {
+            // Falling behind the display, so do less work per frame
+            self.ticks = ((self.ticks as f64 * THROTTLE_STEP) as usize).max(1);
+            if self.ticks != previous_ticks && !self.quiet {
+                println!("Throttling:\t{} ticks/frame ({} IPS)", self.ticks, self.effective_ips());
+            }
+        }
+        else if self.ticks < self.target_ticks {
+            // Keeping up, so work back towards the target
+            self.ticks = (self.ticks + (self.target_ticks / RECOVERY_STEP).max(1)).min(self.target_ticks);
+            if self.ticks == self.target_ticks && !self.quiet {
+                println!("Recovered:\t{} ticks/frame ({} IPS)", self.ticks, self.effective_ips());
+            }
+        }
+
+        self.ticks != previous_ticks
+    }
+}
@@ -0,0 +1,31 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: screenshot.rs                      //
+// Description: PNG export of the screen.   //
+// ---------------------------------------- //
+
+use crate::render::{COLOR_OFF, COLOR_ON};
+use chip8_rust::chip8::Chip8;
+
+use std::fs::File;
+use std::io::{BufWriter, Error};
+
+use png::{BitDepth, ColorType, Encoder};
+
+/// Writes the screen to a PNG file at its native resolution (one image pixel per CHIP-8 pixel).
+pub fn save_png(chip8: &Chip8, path: &str) -> Result<(), Error> {
+    let rgba: Vec<u8> = chip8.framebuffer_rgba(
+        [COLOR_ON.r, COLOR_ON.g, COLOR_ON.b, 0xFF],
+        [COLOR_OFF.r, COLOR_OFF.g, COLOR_OFF.b, 0xFF],
+    );
+
+    let file: BufWriter<File> = BufWriter::new(File::create(path)?);
+    let mut encoder: Encoder<BufWriter<File>> = Encoder::new(file, chip8.width() as u32, chip8.height() as u32);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(Error::other)?;
+    writer.write_image_data(&rgba).map_err(Error::other)
+}
@@ -0,0 +1,79 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: stats.rs                           //
+// Description: Rolling frame timing        //
+//              reports.                    //
+// ---------------------------------------- //
+
+use std::time::{Duration, Instant};
+
+/// Collects frame times and instruction counts, printing a summary every few frames.
+pub struct FrameStats {
+    /// Frames between reports.
+    interval: u32,
+    /// Frames recorded since the last report.
+    frames: u32,
+    /// Shortest frame since the last report.
+    min: Duration,
+    /// Longest frame since the last report.
+    max: Duration,
+    /// When the last report was printed, or when recording started.
+    report_start: Instant,
+    /// When the previous frame ended.
+    last_frame: Instant,
+    /// Instructions run in total as of the last report.
+    report_cycles: u64,
+}
+
+impl FrameStats {
+    /// Initializes statistics that report every `interval` frames, starting now.
+    pub fn new(interval: u32) -> FrameStats {
+        let now: Instant = Instant::now();
+        FrameStats {
+            interval: interval.max(1),
+            frames: 0,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            report_start: now,
+            last_frame: now,
+            report_cycles: 0,
+        }
+    }
+
+    /// Records the end of a frame, given the total instructions run so far, printing a report once `interval`
+    /// frames have ended since the last one.
+    pub fn end_frame(&mut self, executed: u64) {
+        let now: Instant = Instant::now();
+        let elapsed: Duration = now - self.last_frame;
+        self.last_frame = now;
+        self.frames += 1;
+        self.min = self.min.min(elapsed);
+        self.max = self.max.max(elapsed);
+        if self.frames < self.interval {
+            return;
+        }
+
+        // Average over the whole window so the FPS and average agree
+        let window: Duration = now - self.report_start;
+        let avg: Duration = window / self.frames;
+        let fps: f64 = self.frames as f64 / window.as_secs_f64();
+        println!(
+            "Stats:\t\t{} frames, {:.2}/{:.2}/{:.2} ms min/avg/max, {:.1} FPS, {} cycles",
+            self.frames,
+            self.min.as_secs_f64() * 1000.0,
+            avg.as_secs_f64() * 1000.0,
+            self.max.as_secs_f64() * 1000.0,
+            fps,
+            executed - self.report_cycles
+        );
+
+        self.frames = 0;
+        self.min = Duration::MAX;
+        self.max = Duration::ZERO;
+        self.report_start = now;
+        self.report_cycles = executed;
+    }
+}
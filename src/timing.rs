@@ -0,0 +1,52 @@
+// ---------------------------------------- //
+// Project: chip8-rust                      //
+//  Author: Kai NeSmith                     //
+//    Date: August 2024                     //
+// ---------------------------------------- //
+// File: timing.rs                          //
+// Description: Per-instruction cost model. //
+// ---------------------------------------- //
+
+/// Default relative cost of each opcode group, indexed by the opcode's first nibble.
+/// On the COSMAC VIP, clearing the screen and drawing sprites took several times longer than register
+/// operations, and calls, random numbers and the FX group fell somewhere in between.
+pub const DEFAULT_COSTS: [u32; 16] = [
+    4, // 0: 00E0 / 00EE
+    1, // 1: jump
+    2, // 2: call
+    1, // 3: skip
+    1, // 4: skip
+    1, // 5: skip
+    1, // 6: load
+    1, // 7: add
+    1, // 8: ALU
+    1, // 9: skip
+    1, // A: load I
+    1, // B: jump offset
+    2, // C: random
+    8, // D: draw
+    1, // E: key skips
+    2, // F: timers, memory and keys
+];
+
+/// Represents how much of a frame's instruction budget each opcode uses up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostTable {
+    /// Cost of each opcode group, indexed by the opcode's first nibble.
+    pub costs: [u32; 16],
+}
+
+impl Default for CostTable {
+    fn default() -> Self {
+        CostTable {
+            costs: DEFAULT_COSTS,
+        }
+    }
+}
+
+impl CostTable {
+    /// Gets the cost of an opcode.
+    pub fn cost(&self, opcode: u16) -> u32 {
+        self.costs[(opcode >> 12) as usize]
+    }
+}